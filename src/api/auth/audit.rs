@@ -0,0 +1,50 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::sync::{Mutex, OnceLock};
+
+// One line of `AUDIT_FILE` for every request to a protected endpoint,
+// whether or not it was let through.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub at: DateTime<Utc>,
+    // The key id or JWT subject, when the caller could be identified.
+    pub principal: Option<String>,
+    pub method: String,
+    pub path: String,
+    pub scope: String,
+    // "allowed", "forbidden" or "unauthenticated".
+    pub outcome: String,
+    pub status: u16,
+    pub ip: Option<String>,
+}
+
+pub fn record(file: &str, entry: &AuditEntry) {
+    static WRITE: OnceLock<Mutex<()>> = OnceLock::new();
+    let _lock = WRITE.get_or_init(|| Mutex::new(())).lock().unwrap();
+    let result = serde_json::to_string(entry)
+        .map_err(|e| e.to_string())
+        .and_then(|line| {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(file)
+                .and_then(|mut out| writeln!(out, "{}", line))
+                .map_err(|e| e.to_string())
+        });
+    if let Err(e) = result {
+        eprintln!("Failed to write the audit trail to {}: {}", file, e);
+    }
+}
+
+// The last `limit` entries, newest first.
+pub fn recent(file: &str, limit: usize) -> Vec<AuditEntry> {
+    let text = fs::read_to_string(file).unwrap_or_default();
+    text.lines()
+        .rev()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .take(limit)
+        .collect()
+}
@@ -0,0 +1,119 @@
+use crate::api::auth::{audit, keys};
+use crate::config::env::{self, ApiKey};
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateKey {
+    id: Option<String>,
+    scopes: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditQuery {
+    limit: Option<usize>,
+}
+
+// Both kinds of key, without their hashes.
+pub async fn list_keys() -> HttpResponse {
+    let config = env::load_env_config().auth;
+    let describe = |key: &ApiKey, source: &str| {
+        json!({
+            "id": key.id,
+            "scopes": key.scopes,
+            "createdAt": key.created_at,
+            "source": source,
+        })
+    };
+    let mut listed: Vec<_> = config
+        .keys
+        .iter()
+        .map(|key| describe(key, "config"))
+        .collect();
+    listed.extend(keys::keys().keys.values().map(|key| describe(key, "store")));
+    HttpResponse::Ok().json(listed)
+}
+
+// The key itself is in the response only; just its hash is kept.
+pub async fn create_key(body: web::Json<CreateKey>) -> HttpResponse {
+    let body = body.into_inner();
+    if body.scopes.is_empty() {
+        return bad_request("give the key at least one scope".to_string());
+    }
+    if let Some(scope) = body
+        .scopes
+        .iter()
+        .find(|scope| !keys::SCOPES.contains(&scope.as_str()))
+    {
+        return bad_request(format!(
+            "unknown scope {}; use {}",
+            scope,
+            keys::SCOPES.join(", ")
+        ));
+    }
+    let id = body
+        .id
+        .unwrap_or_else(|| format!("key-{:016x}", rand::random::<u64>()));
+    let config = env::load_env_config().auth;
+    let mut store = keys::keys();
+    if store.keys.contains_key(&id) || config.keys.iter().any(|key| key.id == id) {
+        return HttpResponse::Conflict()
+            .json(json!({ "error": format!("key {} already exists", id) }));
+    }
+
+    let key = keys::generate();
+    let created_at = Utc::now();
+    store.keys.insert(
+        id.clone(),
+        ApiKey {
+            id: id.clone(),
+            hash: keys::hash(&key),
+            scopes: body.scopes.clone(),
+            created_at: Some(created_at),
+        },
+    );
+    store.save();
+    println!("Created API key {} with scopes {:?}", id, body.scopes);
+    HttpResponse::Created().json(json!({
+        "id": id,
+        "key": key,
+        "scopes": body.scopes,
+        "createdAt": created_at,
+    }))
+}
+
+pub async fn revoke_key(id: web::Path<String>) -> HttpResponse {
+    let mut store = keys::keys();
+    match store.keys.remove(id.as_str()) {
+        Some(_) => {
+            store.save();
+            println!("Revoked API key {}", id);
+            HttpResponse::Ok().json(json!({ "id": id.as_str(), "revoked": true }))
+        }
+        None if env::load_env_config()
+            .auth
+            .keys
+            .iter()
+            .any(|key| key.id == id.as_str()) =>
+        {
+            HttpResponse::Conflict().json(
+                json!({ "error": "the key is set in API_KEYS and can only be removed there" }),
+            )
+        }
+        None => HttpResponse::NotFound().json(json!({ "error": "no such key" })),
+    }
+}
+
+pub async fn audit_trail(query: web::Query<AuditQuery>) -> HttpResponse {
+    let config = env::load_env_config().auth;
+    HttpResponse::Ok().json(audit::recent(
+        &config.audit_file,
+        query.limit.unwrap_or(100),
+    ))
+}
+
+fn bad_request(error: String) -> HttpResponse {
+    HttpResponse::BadRequest().json(json!({ "error": error }))
+}
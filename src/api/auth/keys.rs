@@ -0,0 +1,230 @@
+use crate::config::env::{self, ApiKey, AuthConfig};
+use actix_web::http::header::HeaderMap;
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+
+// `admin` grants every other scope as well.
+pub const SCOPES: [&str; 3] = ["calls:create", "calls:read", "admin"];
+
+// Held only by Vapi itself, through the webhook secret; no key can be given
+// it.
+pub const WEBHOOK: &str = "webhook";
+
+// Who made a request: an API key by its id, or a JWT by its subject.
+pub struct Principal {
+    pub id: String,
+    pub scopes: Vec<String>,
+}
+
+impl Principal {
+    pub fn allows(&self, scope: &str) -> bool {
+        self.scopes
+            .iter()
+            .any(|granted| granted == scope || (granted == "admin" && scope != WEBHOOK))
+    }
+}
+
+// HS256 tokens signed with `JWT_SECRET`. Scopes are space separated, as in
+// OAuth: {"sub": "crm", "scope": "calls:read calls:create", "exp": 1790000000}
+#[derive(Deserialize)]
+struct Claims {
+    sub: String,
+    #[serde(default)]
+    scope: String,
+}
+
+// Keys created through the API, kept hashed in `API_KEYS_FILE`. Keys set in
+// `API_KEYS` are accepted too, but can't be changed through the API.
+pub struct KeyStore {
+    file: String,
+    pub keys: BTreeMap<String, ApiKey>,
+}
+
+pub fn keys() -> MutexGuard<'static, KeyStore> {
+    static KEYS: OnceLock<Mutex<KeyStore>> = OnceLock::new();
+    KEYS.get_or_init(|| Mutex::new(KeyStore::load(&env::load_env_config().auth)))
+        .lock()
+        .unwrap()
+}
+
+impl KeyStore {
+    fn load(config: &AuthConfig) -> Self {
+        let keys: Vec<ApiKey> = match fs::read_to_string(&config.keys_file) {
+            Ok(text) => serde_json::from_str(&text).unwrap_or_else(|e| {
+                eprintln!("Ignoring invalid API key file {}: {}", config.keys_file, e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        if keys.is_empty() && config.keys.is_empty() && config.jwt_secret.is_none() {
+            eprintln!(
+                "No API keys or JWT_SECRET are set; control endpoints will refuse every request"
+            );
+        }
+        KeyStore {
+            file: config.keys_file.clone(),
+            keys: keys.into_iter().map(|key| (key.id.clone(), key)).collect(),
+        }
+    }
+
+    // Written to a temporary file first so a crash can't leave half a file.
+    pub fn save(&self) {
+        let keys: Vec<&ApiKey> = self.keys.values().collect();
+        let temp = format!("{}.tmp", self.file);
+        let result = serde_json::to_string_pretty(&keys)
+            .map_err(|e| e.to_string())
+            .and_then(|text| fs::write(&temp, text).map_err(|e| e.to_string()))
+            .and_then(|_| fs::rename(&temp, &self.file).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            eprintln!("Failed to save API keys to {}: {}", self.file, e);
+        }
+    }
+}
+
+// Called at startup, so a missing secret shows up before Vapi's first call
+// does rather than as a 401 on it.
+pub fn check_vapi_secret(config: &AuthConfig) {
+    if config.disabled || config.webhook_secret.is_some() {
+        return;
+    }
+    if config.vapi_disabled {
+        eprintln!(
+            "VAPI_AUTH_DISABLED is set; the webhook, inbound, function and custom LLM routes accept requests from anyone"
+        );
+    } else {
+        eprintln!(
+            "VAPI_WEBHOOK_SECRET is not set; the webhook, inbound, function and custom LLM routes will refuse every request. Set it to the server secret configured in Vapi, or set VAPI_AUTH_DISABLED=true to leave them open"
+        );
+    }
+}
+
+pub fn hash(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+pub fn generate() -> String {
+    format!(
+        "vsk_{:032x}{:032x}",
+        rand::random::<u128>(),
+        rand::random::<u128>()
+    )
+}
+
+// Keys come in an `X-API-Key` header or as a bearer token; a bearer token
+// shaped like a JWT is checked as one when `JWT_SECRET` is set.
+pub fn authenticate(config: &AuthConfig, headers: &HeaderMap) -> Result<Principal, String> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let token = header("X-API-Key")
+        .or_else(|| header("Authorization").and_then(|value| value.strip_prefix("Bearer ")))
+        .map(str::trim)
+        .ok_or_else(|| "no API key or bearer token".to_string())?;
+
+    if let Some(secret) = &config.jwt_secret {
+        if token.split('.').count() == 3 {
+            let claims = decode::<Claims>(
+                token,
+                &DecodingKey::from_secret(secret.as_bytes()),
+                &Validation::new(Algorithm::HS256),
+            )
+            .map_err(|e| format!("invalid token: {}", e))?
+            .claims;
+            return Ok(Principal {
+                id: format!("jwt:{}", claims.sub),
+                scopes: claims
+                    .scope
+                    .split_whitespace()
+                    .map(str::to_string)
+                    .collect(),
+            });
+        }
+    }
+
+    let hash = hash(token);
+    let stored = keys().keys.values().find(|key| key.hash == hash).cloned();
+    stored
+        .or_else(|| config.keys.iter().find(|key| key.hash == hash).cloned())
+        .map(|key| Principal {
+            id: key.id,
+            scopes: key.scopes,
+        })
+        .ok_or_else(|| "unknown API key".to_string())
+}
+
+// Checks the `X-Vapi-Secret` Vapi sends with webhooks, inbound calls, function
+// calls and custom LLM turns. Digests are compared so the time taken says
+// nothing about how much of the secret matched.
+pub fn vapi(config: &AuthConfig, headers: &HeaderMap) -> Result<Principal, String> {
+    let secret = config
+        .webhook_secret
+        .as_deref()
+        .ok_or_else(|| "VAPI_WEBHOOK_SECRET is not set".to_string())?;
+    let given = headers
+        .get("X-Vapi-Secret")
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| "no X-Vapi-Secret".to_string())?;
+    if Sha256::digest(given.as_bytes()) != Sha256::digest(secret.as_bytes()) {
+        return Err("wrong X-Vapi-Secret".to_string());
+    }
+    Ok(Principal {
+        id: "vapi".to_string(),
+        scopes: vec![WEBHOOK.to_string()],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::header::{HeaderName, HeaderValue};
+
+    fn config(webhook_secret: Option<&str>) -> AuthConfig {
+        AuthConfig {
+            disabled: false,
+            keys: Vec::new(),
+            keys_file: String::new(),
+            jwt_secret: None,
+            webhook_secret: webhook_secret.map(str::to_string),
+            vapi_disabled: false,
+            audit_file: String::new(),
+        }
+    }
+
+    fn headers(secret: Option<&str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(secret) = secret {
+            headers.insert(
+                HeaderName::from_static("x-vapi-secret"),
+                HeaderValue::from_str(secret).unwrap(),
+            );
+        }
+        headers
+    }
+
+    #[test]
+    fn webhooks_need_the_vapi_secret() {
+        let config = config(Some("s3cret"));
+        let principal = vapi(&config, &headers(Some("s3cret"))).unwrap();
+        assert!(principal.allows(WEBHOOK));
+        assert!(!principal.allows("calls:read"));
+        assert!(vapi(&config, &headers(Some("s3cre"))).is_err());
+        assert!(vapi(&config, &headers(None)).is_err());
+    }
+
+    #[test]
+    fn webhooks_are_refused_without_a_configured_secret() {
+        assert!(vapi(&config(None), &headers(Some(""))).is_err());
+    }
+
+    #[test]
+    fn admin_keys_do_not_open_the_webhook() {
+        let admin = Principal {
+            id: "ops".to_string(),
+            scopes: vec!["admin".to_string()],
+        };
+        assert!(admin.allows("calls:create"));
+        assert!(!admin.allows(WEBHOOK));
+    }
+}
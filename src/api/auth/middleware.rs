@@ -0,0 +1,125 @@
+use crate::api::auth::{
+    audit::{self, AuditEntry},
+    keys,
+};
+use crate::config::env;
+use actix_web::{
+    body::EitherBody,
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::{header, StatusCode},
+    Error, HttpResponse,
+};
+use chrono::Utc;
+use futures::future::{ready, LocalBoxFuture, Ready};
+use serde_json::json;
+
+// Lets a request through only when its API key or token carries the scope,
+// or, for `keys::WEBHOOK`, when it carries Vapi's webhook secret. Wrapped
+// around each protected route in `routes::config`, including every route Vapi
+// calls.
+pub struct Require(pub &'static str);
+
+impl<S, B> Transform<S, ServiceRequest> for Require
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = RequireMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequireMiddleware {
+            service,
+            scope: self.0,
+        }))
+    }
+}
+
+pub struct RequireMiddleware<S> {
+    service: S,
+    scope: &'static str,
+}
+
+impl<S, B> Service<ServiceRequest> for RequireMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let config = env::load_env_config().auth;
+        if config.disabled || (self.scope == keys::WEBHOOK && config.vapi_disabled) {
+            let response = self.service.call(req);
+            return Box::pin(
+                async move { response.await.map(ServiceResponse::map_into_left_body) },
+            );
+        }
+
+        let mut entry = AuditEntry {
+            at: Utc::now(),
+            principal: None,
+            method: req.method().to_string(),
+            path: req.path().to_string(),
+            scope: self.scope.to_string(),
+            outcome: "allowed".to_string(),
+            status: 0,
+            ip: req
+                .connection_info()
+                .realip_remote_addr()
+                .map(str::to_string),
+        };
+        let principal = if self.scope == keys::WEBHOOK {
+            keys::vapi(&config, req.headers())
+        } else {
+            keys::authenticate(&config, req.headers())
+        };
+        let refusal = match principal {
+            Ok(principal) if principal.allows(self.scope) => {
+                entry.principal = Some(principal.id);
+                None
+            }
+            Ok(principal) => {
+                let reason = format!("{} lacks the {} scope", principal.id, self.scope);
+                entry.principal = Some(principal.id);
+                Some((StatusCode::FORBIDDEN, "forbidden", reason))
+            }
+            Err(reason) => Some((StatusCode::UNAUTHORIZED, "unauthenticated", reason)),
+        };
+
+        match refusal {
+            None => {
+                let response = self.service.call(req);
+                Box::pin(async move {
+                    let response = response.await;
+                    entry.status = match &response {
+                        Ok(response) => response.status().as_u16(),
+                        Err(e) => e.as_response_error().status_code().as_u16(),
+                    };
+                    audit::record(&config.audit_file, &entry);
+                    response.map(ServiceResponse::map_into_left_body)
+                })
+            }
+            Some((status, outcome, reason)) => {
+                entry.outcome = outcome.to_string();
+                entry.status = status.as_u16();
+                audit::record(&config.audit_file, &entry);
+                let mut response = HttpResponse::build(status);
+                if status == StatusCode::UNAUTHORIZED {
+                    response.insert_header((header::WWW_AUTHENTICATE, "Bearer"));
+                }
+                let response = response.json(json!({ "error": reason }));
+                Box::pin(ready(Ok(req.into_response(response).map_into_right_body())))
+            }
+        }
+    }
+}
@@ -0,0 +1,4 @@
+pub mod audit;
+pub mod index;
+pub mod keys;
+pub mod middleware;
@@ -0,0 +1,44 @@
+use crate::api::calls::lifecycle::{self, CallRecord, CallState};
+use actix_web::{web, HttpResponse};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Debug, Deserialize)]
+pub struct ListCalls {
+    state: Option<String>,
+}
+
+// Newest first, without their histories.
+pub async fn list(query: web::Query<ListCalls>) -> HttpResponse {
+    let state = match query.state.as_deref() {
+        Some(state) => match serde_json::from_value::<CallState>(json!(state)) {
+            Ok(state) => Some(state),
+            Err(_) => {
+                return HttpResponse::BadRequest().json(json!({
+                    "error": format!("{} is not a call state", state),
+                    "field": "state",
+                }))
+            }
+        },
+        None => None,
+    };
+    let calls = lifecycle::calls();
+    let mut records: Vec<&CallRecord> = calls
+        .values()
+        .filter(|record| state.is_none_or(|state| record.state == state))
+        .collect();
+    records.sort_by_key(|record| std::cmp::Reverse(record.created_at));
+    HttpResponse::Ok().json(
+        records
+            .into_iter()
+            .map(CallRecord::summary)
+            .collect::<Vec<_>>(),
+    )
+}
+
+pub async fn get(id: web::Path<String>) -> HttpResponse {
+    match lifecycle::calls().get(id.as_str()) {
+        Some(record) => HttpResponse::Ok().json(record),
+        None => HttpResponse::NotFound().json(json!({ "error": "no such call" })),
+    }
+}
@@ -0,0 +1,267 @@
+use crate::api::custom_llm::session;
+use crate::types::vapi::VapiCall;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CallState {
+    Created,
+    Ringing,
+    InProgress,
+    Forwarding,
+    Ended,
+}
+
+impl CallState {
+    // Vapi's call statuses. "queued" is a call Vapi has not started dialing
+    // yet, which is still just created as far as we are concerned.
+    pub fn from_status(status: &str) -> Option<CallState> {
+        match status {
+            "queued" => Some(CallState::Created),
+            "ringing" => Some(CallState::Ringing),
+            "in-progress" => Some(CallState::InProgress),
+            "forwarding" => Some(CallState::Forwarding),
+            "ended" => Some(CallState::Ended),
+            _ => None,
+        }
+    }
+
+    // A call can end from any state, and inbound calls are answered without
+    // ringing first.
+    fn can_become(self, next: CallState) -> bool {
+        matches!(
+            (self, next),
+            (CallState::Created, CallState::Ringing)
+                | (CallState::Created, CallState::InProgress)
+                | (CallState::Ringing, CallState::InProgress)
+                | (CallState::InProgress, CallState::Forwarding)
+                | (
+                    CallState::Created
+                        | CallState::Ringing
+                        | CallState::InProgress
+                        | CallState::Forwarding,
+                    CallState::Ended
+                )
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Transition {
+    pub from: Option<CallState>,
+    pub to: CallState,
+    // What reported it: "created", "status-update", "hang",
+    // "end-of-call-report" or "reconcile".
+    pub event: String,
+    pub at: DateTime<Utc>,
+    // Invalid transitions are kept in the history but not applied. Events
+    // that don't change the state, such as "hang", have the same `from`
+    // and `to`.
+    pub applied: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallRecord {
+    pub id: String,
+    // Vapi's call type, such as "outboundPhoneCall" or "inboundPhoneCall".
+    pub call_type: Option<String>,
+    pub state: CallState,
+    pub customer_number: Option<String>,
+    pub assistant_id: Option<String>,
+    pub ended_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub history: Vec<Transition>,
+}
+
+impl CallRecord {
+    pub fn summary(&self) -> Value {
+        json!({
+            "id": self.id,
+            "type": self.call_type,
+            "state": self.state,
+            "customerNumber": self.customer_number,
+            "assistantId": self.assistant_id,
+            "endedReason": self.ended_reason,
+            "createdAt": self.created_at,
+            "updatedAt": self.updated_at,
+        })
+    }
+}
+
+pub fn calls() -> MutexGuard<'static, HashMap<String, CallRecord>> {
+    static CALLS: OnceLock<Mutex<HashMap<String, CallRecord>>> = OnceLock::new();
+    CALLS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap()
+}
+
+// Starts tracking a call the first time we hear of it, either because we
+// placed it or because a webhook mentions it. Later sightings only fill in
+// details that were missing.
+pub fn track(call: &VapiCall, event: &str) {
+    let id = match &call.id {
+        Some(id) => id,
+        None => return,
+    };
+    let now = Utc::now();
+    let mut calls = calls();
+    let record = calls.entry(id.clone()).or_insert_with(|| CallRecord {
+        id: id.clone(),
+        call_type: None,
+        state: CallState::Created,
+        customer_number: None,
+        assistant_id: None,
+        ended_reason: None,
+        created_at: now,
+        updated_at: now,
+        history: vec![Transition {
+            from: None,
+            to: CallState::Created,
+            event: event.to_string(),
+            at: now,
+            applied: true,
+        }],
+    });
+    if record.call_type.is_none() {
+        record.call_type = call.call_type.clone();
+    }
+    if record.customer_number.is_none() {
+        record.customer_number = call
+            .customer
+            .as_ref()
+            .and_then(|customer| customer.number.clone());
+    }
+    if record.assistant_id.is_none() {
+        record.assistant_id = call.assistant_id.clone();
+    }
+}
+
+// Moves a call to `next` if the state machine allows it. Repeated events
+// for the state a call is already in are ignored; anything else that is not
+// allowed is logged and recorded. Reconciliation may skip states whose
+// webhooks never arrived, as long as it only moves the call forward.
+pub fn transition(call: &VapiCall, next: CallState, event: &str) {
+    track(call, event);
+    let id = match &call.id {
+        Some(id) => id,
+        None => return,
+    };
+    let mut calls = calls();
+    let record = match calls.get_mut(id) {
+        Some(record) => record,
+        None => return,
+    };
+    if record.state == next {
+        return;
+    }
+
+    let applied = if event == "reconcile" {
+        next > record.state
+    } else {
+        record.state.can_become(next)
+    };
+    let now = Utc::now();
+    record.history.push(Transition {
+        from: Some(record.state),
+        to: next,
+        event: event.to_string(),
+        at: now,
+        applied,
+    });
+    if !applied {
+        eprintln!(
+            "Ignoring invalid transition of call {} from {:?} to {:?} ({})",
+            id, record.state, next, event
+        );
+        return;
+    }
+    record.state = next;
+    record.updated_at = now;
+    if next == CallState::Ended {
+        if record.ended_reason.is_none() {
+            record.ended_reason = call.ended_reason.clone();
+        }
+        drop(calls);
+        session::ended(id);
+    }
+}
+
+// Notes an event in the call's history without moving it. Vapi sends
+// "hang" when the assistant stalls, while the call itself goes on.
+pub fn event(call: &VapiCall, event: &str) {
+    track(call, event);
+    let id = match &call.id {
+        Some(id) => id,
+        None => return,
+    };
+    if let Some(record) = calls().get_mut(id) {
+        record.history.push(Transition {
+            from: Some(record.state),
+            to: record.state,
+            event: event.to_string(),
+            at: Utc::now(),
+            applied: true,
+        });
+    }
+}
+
+// End-of-call reports carry the reason alongside the call rather than on it.
+pub fn ended(call: &VapiCall, ended_reason: &str, event: &str) {
+    transition(call, CallState::Ended, event);
+    let id = match &call.id {
+        Some(id) => id,
+        None => return,
+    };
+    if let Some(record) = calls().get_mut(id) {
+        if record.state == CallState::Ended {
+            record.ended_reason = Some(ended_reason.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(id: &str) -> VapiCall {
+        VapiCall {
+            id: Some(id.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn hang_is_recorded_without_ending_the_call() {
+        let call = call("test-hang");
+        track(&call, "created");
+        transition(&call, CallState::InProgress, "status-update");
+        event(&call, "hang");
+        assert_eq!(calls()["test-hang"].state, CallState::InProgress);
+
+        transition(&call, CallState::Forwarding, "status-update");
+        ended(&call, "customer-ended-call", "end-of-call-report");
+        let record = calls()["test-hang"].clone();
+        assert_eq!(record.state, CallState::Ended);
+        assert_eq!(record.ended_reason.as_deref(), Some("customer-ended-call"));
+        assert!(record.history.iter().all(|transition| transition.applied));
+        assert_eq!(record.history.len(), 5);
+    }
+
+    #[test]
+    fn invalid_transitions_are_kept_but_not_applied() {
+        let call = call("test-invalid");
+        transition(&call, CallState::InProgress, "status-update");
+        transition(&call, CallState::Ringing, "status-update");
+        let record = calls()["test-invalid"].clone();
+        assert_eq!(record.state, CallState::InProgress);
+        assert!(!record.history.last().unwrap().applied);
+    }
+}
@@ -0,0 +1,58 @@
+use crate::api::calls::lifecycle::{self, CallState};
+use crate::api::campaign;
+use crate::api::schedule;
+use crate::config::env;
+use crate::vapi_client::VapiApi;
+use actix_web::rt::time::sleep;
+use chrono::{Duration as ChronoDuration, Utc};
+use std::sync::Arc;
+use std::time::Duration;
+
+// Asks Vapi about calls that have not ended and have not changed for a
+// while, in case their webhooks were lost or the server was down when they
+// were sent.
+pub async fn run(vapi: Arc<dyn VapiApi>) {
+    loop {
+        let config = env::load_env_config().calls;
+        for id in stale(config.reconcile_after_secs) {
+            match vapi.get_call(&id).await {
+                Ok(call) => {
+                    let status = call.status.clone().unwrap_or_default();
+                    match CallState::from_status(&status) {
+                        Some(CallState::Ended) => {
+                            let ended_reason = call.ended_reason.clone().unwrap_or_default();
+                            lifecycle::ended(&call, &ended_reason, "reconcile");
+                            campaign::store::call_ended(&id, &ended_reason);
+                            schedule::jobs::call_ended(&id, &ended_reason);
+                        }
+                        Some(state) => {
+                            lifecycle::transition(&call, state, "reconcile");
+                            campaign::store::call_status(&id, &status);
+                        }
+                        None => {}
+                    }
+                    touch(&id);
+                }
+                Err(e) => eprintln!("Failed to reconcile call {}: {}", id, e),
+            }
+        }
+        sleep(Duration::from_millis(config.reconcile_interval_ms)).await;
+    }
+}
+
+fn stale(after_secs: i64) -> Vec<String> {
+    let cutoff = Utc::now() - ChronoDuration::seconds(after_secs);
+    lifecycle::calls()
+        .values()
+        .filter(|record| record.state != CallState::Ended && record.updated_at <= cutoff)
+        .map(|record| record.id.clone())
+        .collect()
+}
+
+// A call Vapi still reports in the same state is checked again only after
+// another full wait.
+fn touch(id: &str) {
+    if let Some(record) = lifecycle::calls().get_mut(id) {
+        record.updated_at = Utc::now();
+    }
+}
@@ -0,0 +1,89 @@
+use crate::api::campaign::store::{Contact, ContactState};
+use crate::api::outbound::is_e164;
+use chrono_tz::Tz;
+use serde_json::{Map, Value};
+
+// Reads a contact list with a header row. The `number` column is the number
+// to dial, `name` the customer's name and `time_zone` the IANA time zone the
+// calling window is applied in, when it is not the campaign's. Every column,
+// those included, is
+// passed to the assistant as a variable named after its header. Rows without
+// a valid E.164 number are reported back instead of being dialed.
+pub fn parse(csv_text: &str) -> Result<(Vec<Contact>, Vec<String>), String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(csv_text.as_bytes());
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|e| format!("invalid CSV header: {}", e))?
+        .iter()
+        .map(str::to_string)
+        .collect();
+    let number_column = headers
+        .iter()
+        .position(|header| header.eq_ignore_ascii_case("number"))
+        .ok_or("the CSV has no \"number\" column")?;
+    let name_column = headers
+        .iter()
+        .position(|header| header.eq_ignore_ascii_case("name"));
+    let time_zone_column = headers.iter().position(|header| {
+        header.eq_ignore_ascii_case("time_zone") || header.eq_ignore_ascii_case("timezone")
+    });
+
+    let mut contacts = Vec::new();
+    let mut skipped = Vec::new();
+    for (row, record) in reader.records().enumerate() {
+        // Row 1 is the header.
+        let line = row + 2;
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                skipped.push(format!("row {}: {}", line, e));
+                continue;
+            }
+        };
+        let number = match record.get(number_column) {
+            Some(number) if !number.is_empty() => number.to_string(),
+            _ => {
+                skipped.push(format!("row {}: no number", line));
+                continue;
+            }
+        };
+        if !is_e164(&number) {
+            skipped.push(format!("row {}: {} is not an E.164 number", line, number));
+            continue;
+        }
+        let time_zone = time_zone_column
+            .and_then(|column| record.get(column))
+            .filter(|time_zone| !time_zone.is_empty());
+        if let Some(time_zone) = time_zone {
+            if time_zone.parse::<Tz>().is_err() {
+                skipped.push(format!("row {}: unknown time zone {}", line, time_zone));
+                continue;
+            }
+        }
+        let variables: Map<String, Value> = headers
+            .iter()
+            .zip(record.iter())
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(header, value)| (header.clone(), Value::String(value.to_string())))
+            .collect();
+        contacts.push(Contact {
+            number,
+            name: name_column
+                .and_then(|column| record.get(column))
+                .filter(|name| !name.is_empty())
+                .map(str::to_string),
+            time_zone: time_zone.map(str::to_string),
+            variables,
+            state: ContactState::Queued,
+            call_id: None,
+            ended_reason: None,
+            error: None,
+            attempts: Vec::new(),
+            retry_at: None,
+        });
+    }
+    Ok((contacts, skipped))
+}
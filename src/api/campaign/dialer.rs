@@ -0,0 +1,290 @@
+use crate::api::calls::lifecycle;
+use crate::api::campaign::store::{self, Campaign, CampaignStatus, Contact, ContactState};
+use crate::api::registry::store as registry;
+use crate::api::retry::{self, RetryPolicy};
+use crate::api::schedule::calendar;
+use crate::config::env::{self, CallingWindow};
+use crate::types::vapi::{AssistantOverrides, CreateCall, VapiCustomer};
+use crate::vapi_client::{VapiApi, VapiError};
+use actix_web::rt::time::sleep;
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+
+enum Next {
+    Dial(usize, Box<Contact>),
+    Wait,
+    Stop,
+}
+
+// Dials a campaign's queued contacts one at a time, waiting the campaign's
+// interval between calls and while it has as many calls going as it allows.
+// Contacts are only dialed inside the calling window, in their time zone.
+// Runs until the campaign is cancelled or every contact has been called.
+pub async fn run(campaign_id: String, vapi: Arc<dyn VapiApi>) {
+    loop {
+        let window = env::load_env_config().schedule.calling_window;
+        let (next, interval, assistant_id, phone_number_id, retry) = {
+            let mut campaigns = store::campaigns();
+            let campaign = match campaigns.campaigns.get_mut(&campaign_id) {
+                Some(campaign) => campaign,
+                None => return,
+            };
+            let active = campaign
+                .contacts
+                .iter()
+                .filter(|contact| contact.state.is_active())
+                .count();
+            let now = Utc::now();
+            hold_outside_window(campaign, &window, now);
+            // Contacts waiting to be retried keep the campaign going.
+            let waiting = campaign
+                .contacts
+                .iter()
+                .any(|contact| contact.state == ContactState::Queued);
+            let queued = campaign.contacts.iter().position(|contact| {
+                contact.state == ContactState::Queued
+                    && contact.retry_at.is_none_or(|retry_at| retry_at <= now)
+            });
+            let next = match (campaign.status, queued) {
+                (CampaignStatus::Cancelled | CampaignStatus::Completed, _) => Next::Stop,
+                (CampaignStatus::Paused, _) => Next::Wait,
+                (CampaignStatus::Running, None) if active == 0 && !waiting => {
+                    println!("Campaign {} has called every contact", campaign_id);
+                    campaign.status = CampaignStatus::Completed;
+                    Next::Stop
+                }
+                (CampaignStatus::Running, Some(index)) if active < campaign.max_active_calls => {
+                    campaign.contacts[index].state = ContactState::Dialing;
+                    Next::Dial(index, Box::new(campaign.contacts[index].clone()))
+                }
+                (CampaignStatus::Running, _) => Next::Wait,
+            };
+            (
+                next,
+                Duration::from_millis(campaign.dial_interval_ms),
+                campaign.assistant_id.clone(),
+                campaign.phone_number_id.clone(),
+                campaign.retry.clone(),
+            )
+        };
+
+        match next {
+            Next::Stop => return,
+            Next::Wait => {}
+            Next::Dial(index, contact) => {
+                if let Err(reason) = registry::check(&contact.number) {
+                    if let Some(campaign) = store::campaigns().campaigns.get_mut(&campaign_id) {
+                        campaign.contacts[index].state = ContactState::Failed;
+                        campaign.contacts[index].error = Some(reason);
+                    }
+                    // Nothing was dialed, so there is no need to wait.
+                    continue;
+                }
+                let result = dial(
+                    vapi.as_ref(),
+                    &campaign_id,
+                    index,
+                    &contact,
+                    &assistant_id,
+                    &phone_number_id,
+                    &retry,
+                )
+                .await;
+                let mut campaigns = store::campaigns();
+                match result {
+                    Ok(call_id) => {
+                        println!(
+                            "Campaign {} called {} ({})",
+                            campaign_id, contact.number, call_id
+                        );
+                        campaigns.dialed(&campaign_id, index, call_id);
+                    }
+                    Err(e) => {
+                        eprintln!(
+                            "Campaign {} failed to call {}: {}",
+                            campaign_id, contact.number, e
+                        );
+                        if let Some(campaign) = campaigns.campaigns.get_mut(&campaign_id) {
+                            campaign.contacts[index].state = ContactState::Failed;
+                            campaign.contacts[index].error = Some(e.to_string());
+                        }
+                    }
+                }
+            }
+        }
+        sleep(interval).await;
+    }
+}
+
+// Queued contacts whose turn has come while their calling window is closed
+// wait for it to open.
+fn hold_outside_window(campaign: &mut Campaign, window: &CallingWindow, now: DateTime<Utc>) {
+    for index in 0..campaign.contacts.len() {
+        let contact = &campaign.contacts[index];
+        if contact.state != ContactState::Queued
+            || contact.retry_at.is_some_and(|retry_at| retry_at > now)
+        {
+            continue;
+        }
+        let tz = campaign.time_zone(contact);
+        let next = calendar::next_allowed(window, tz, now);
+        let contact = &mut campaign.contacts[index];
+        match next {
+            Some(next) if next == now => {}
+            Some(next) => {
+                println!(
+                    "Campaign {} will call {} at {}, when the calling window opens",
+                    campaign.id, contact.number, next
+                );
+                contact.retry_at = Some(next);
+            }
+            None => {
+                contact.state = ContactState::Failed;
+                contact.error = Some("the calling window allows no time to call".to_string());
+            }
+        }
+    }
+}
+
+// Places the call and returns its id. The contact's columns become the
+// assistant's variable values, and the call carries the campaign in its
+// metadata.
+async fn dial(
+    vapi: &dyn VapiApi,
+    campaign_id: &str,
+    index: usize,
+    contact: &Contact,
+    assistant_id: &str,
+    phone_number_id: &str,
+    retry: &RetryPolicy,
+) -> Result<String, VapiError> {
+    let attempt = contact.attempts.len() + 1;
+    let call = vapi
+        .create_call(
+            &CreateCall {
+                assistant_id: Some(assistant_id.to_string()),
+                phone_number_id: Some(phone_number_id.to_string()),
+                customer: Some(VapiCustomer {
+                    number: Some(contact.number.clone()),
+                    name: contact.name.clone(),
+                }),
+                assistant_overrides: Some(AssistantOverrides {
+                    variable_values: Some(contact.variables.clone()),
+                    voicemail_message: retry.voicemail_message(attempt),
+                    ..Default::default()
+                }),
+                metadata: Some(json!({
+                    "campaignId": campaign_id,
+                    "contact": index,
+                    "attempt": attempt,
+                })),
+                ..Default::default()
+            },
+            // Stable per attempt, so a contact is never called twice for
+            // the same attempt even if the request is retried.
+            Some(&retry::idempotency_key(
+                &format!("{}-contact-{}", campaign_id, index),
+                attempt,
+            )),
+        )
+        .await?;
+    lifecycle::track(&call, "created");
+    call.id
+        .ok_or_else(|| VapiError::Decode("the call has no id".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::retry::RetryPolicy;
+    use chrono::{NaiveDate, NaiveTime, TimeZone, Weekday};
+    use serde_json::Map;
+
+    fn campaign(numbers: &[&str]) -> Campaign {
+        Campaign {
+            id: "campaign-test".to_string(),
+            name: None,
+            assistant_id: "a1".to_string(),
+            phone_number_id: "p1".to_string(),
+            dial_interval_ms: 0,
+            max_active_calls: 1,
+            retry: RetryPolicy::default(),
+            time_zone: "America/New_York".to_string(),
+            status: CampaignStatus::Running,
+            created_at: String::new(),
+            contacts: numbers
+                .iter()
+                .map(|number| Contact {
+                    number: number.to_string(),
+                    name: None,
+                    time_zone: None,
+                    variables: Map::new(),
+                    state: ContactState::Queued,
+                    call_id: None,
+                    ended_reason: None,
+                    error: None,
+                    attempts: Vec::new(),
+                    retry_at: None,
+                })
+                .collect(),
+        }
+    }
+
+    fn window(holidays: Vec<NaiveDate>) -> CallingWindow {
+        CallingWindow {
+            days: vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ],
+            start: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(21, 0, 0).unwrap(),
+            holidays,
+        }
+    }
+
+    #[test]
+    fn contacts_wait_for_the_calling_window() {
+        // 20:55 and 21:05 on Friday 2026-10-16 in New York.
+        let before_close = Utc.with_ymd_and_hms(2026, 10, 17, 0, 55, 0).unwrap();
+        let after_close = Utc.with_ymd_and_hms(2026, 10, 17, 1, 5, 0).unwrap();
+        let monday_open = Utc.with_ymd_and_hms(2026, 10, 19, 12, 0, 0).unwrap();
+
+        let mut campaign = campaign(&["+15550000001", "+15550000002"]);
+        campaign.contacts[1].time_zone = Some("Europe/London".to_string());
+        hold_outside_window(&mut campaign, &window(Vec::new()), before_close);
+        assert_eq!(campaign.contacts[0].retry_at, None);
+        // Already 01:55 on Saturday in London.
+        assert_eq!(
+            campaign.contacts[1].retry_at,
+            Some(Utc.with_ymd_and_hms(2026, 10, 19, 7, 0, 0).unwrap())
+        );
+
+        // A retry that falls due after hours waits for Monday morning.
+        campaign.contacts[0].retry_at = Some(after_close);
+        hold_outside_window(&mut campaign, &window(Vec::new()), after_close);
+        assert_eq!(campaign.contacts[0].retry_at, Some(monday_open));
+
+        // And past a holiday on that Monday.
+        let holiday = NaiveDate::from_ymd_opt(2026, 10, 19).unwrap();
+        campaign.contacts[0].retry_at = Some(after_close);
+        hold_outside_window(&mut campaign, &window(vec![holiday]), after_close);
+        assert_eq!(
+            campaign.contacts[0].retry_at,
+            Some(Utc.with_ymd_and_hms(2026, 10, 20, 12, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn contacts_fail_when_the_window_never_opens() {
+        let mut campaign = campaign(&["+15550000001"]);
+        let mut window = window(Vec::new());
+        window.days.clear();
+        hold_outside_window(&mut campaign, &window, Utc::now());
+        assert_eq!(campaign.contacts[0].state, ContactState::Failed);
+    }
+}
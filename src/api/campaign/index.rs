@@ -0,0 +1,149 @@
+use crate::api::campaign::{
+    contacts, dialer,
+    store::{self, Campaign, CampaignStatus},
+};
+use crate::api::retry::RetryPolicy;
+use crate::config::env;
+use crate::vapi_client::VapiApi;
+use actix_web::{rt, web, HttpResponse};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateCampaign {
+    assistant_id: String,
+    phone_number_id: String,
+    name: Option<String>,
+    dial_interval_ms: Option<u64>,
+    max_active_calls: Option<usize>,
+    // An IANA time zone for contacts without a `time_zone` column.
+    time_zone: Option<String>,
+    // The retry policy for unanswered calls; see `RetryPolicy`.
+    max_attempts: Option<u32>,
+    retry_spacing_secs: Option<i64>,
+    voicemail_message: Option<String>,
+}
+
+// POST /api/campaigns?assistantId=...&phoneNumberId=... with the contact
+// list as the CSV body. Dialing starts straight away.
+pub async fn create(
+    query: web::Query<CreateCampaign>,
+    body: String,
+    vapi: web::Data<dyn VapiApi>,
+) -> HttpResponse {
+    let query = query.into_inner();
+    let (contacts, skipped) = match contacts::parse(&body) {
+        Ok(parsed) => parsed,
+        Err(e) => return HttpResponse::BadRequest().json(json!({ "error": e })),
+    };
+    if contacts.is_empty() {
+        return HttpResponse::BadRequest()
+            .json(json!({ "error": "the CSV has no contacts to call", "skipped": skipped }));
+    }
+
+    let env = env::load_env_config();
+    let time_zone = query
+        .time_zone
+        .unwrap_or_else(|| env.schedule.default_time_zone.clone());
+    if time_zone.parse::<Tz>().is_err() {
+        return HttpResponse::BadRequest()
+            .json(json!({ "error": format!("unknown time zone {}", time_zone) }));
+    }
+    let config = env.campaign;
+    let defaults = RetryPolicy::from(&env.retry);
+    let campaign = Campaign {
+        id: format!("campaign-{:016x}", rand::random::<u64>()),
+        name: query.name,
+        assistant_id: query.assistant_id,
+        phone_number_id: query.phone_number_id,
+        dial_interval_ms: query.dial_interval_ms.unwrap_or(config.dial_interval_ms),
+        max_active_calls: query
+            .max_active_calls
+            .unwrap_or(config.max_active_calls)
+            .max(1),
+        retry: RetryPolicy {
+            max_attempts: query.max_attempts.unwrap_or(defaults.max_attempts),
+            spacing_secs: query.retry_spacing_secs.unwrap_or(defaults.spacing_secs),
+            voicemail_message: query.voicemail_message.or(defaults.voicemail_message),
+        },
+        time_zone,
+        status: CampaignStatus::Running,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        contacts,
+    };
+    let mut summary = campaign.summary();
+    summary["skipped"] = json!(skipped);
+    println!(
+        "Campaign {} created with {} contacts",
+        campaign.id,
+        campaign.contacts.len()
+    );
+
+    let id = campaign.id.clone();
+    store::campaigns().campaigns.insert(id.clone(), campaign);
+    rt::spawn(dialer::run(id, vapi.into_inner()));
+    HttpResponse::Created().json(summary)
+}
+
+pub async fn list() -> HttpResponse {
+    let campaigns = store::campaigns();
+    let mut summaries: Vec<_> = campaigns.campaigns.values().collect();
+    summaries.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+    HttpResponse::Ok().json(
+        summaries
+            .into_iter()
+            .map(Campaign::summary)
+            .collect::<Vec<_>>(),
+    )
+}
+
+pub async fn get(id: web::Path<String>) -> HttpResponse {
+    match store::campaigns().campaigns.get(id.as_str()) {
+        Some(campaign) => HttpResponse::Ok().json(campaign),
+        None => not_found(),
+    }
+}
+
+pub async fn pause(id: web::Path<String>) -> HttpResponse {
+    set_status(&id, CampaignStatus::Paused)
+}
+
+pub async fn resume(id: web::Path<String>) -> HttpResponse {
+    set_status(&id, CampaignStatus::Running)
+}
+
+// Cancelling stops further dialing; calls already going are left to finish.
+pub async fn cancel(id: web::Path<String>) -> HttpResponse {
+    set_status(&id, CampaignStatus::Cancelled)
+}
+
+fn set_status(id: &str, status: CampaignStatus) -> HttpResponse {
+    let mut campaigns = store::campaigns();
+    let campaign = match campaigns.campaigns.get_mut(id) {
+        Some(campaign) => campaign,
+        None => return not_found(),
+    };
+    let allowed = matches!(
+        (campaign.status, status),
+        (CampaignStatus::Running, CampaignStatus::Paused)
+            | (CampaignStatus::Paused, CampaignStatus::Running)
+            | (
+                CampaignStatus::Running | CampaignStatus::Paused,
+                CampaignStatus::Cancelled
+            )
+    );
+    if !allowed {
+        return HttpResponse::Conflict().json(json!({
+            "error": format!("the campaign is {:?}", campaign.status).to_lowercase(),
+        }));
+    }
+    println!("Campaign {} is now {:?}", id, status);
+    campaign.status = status;
+    HttpResponse::Ok().json(campaign.summary())
+}
+
+fn not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(json!({ "error": "no such campaign" }))
+}
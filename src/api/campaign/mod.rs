@@ -0,0 +1,4 @@
+pub mod contacts;
+pub mod dialer;
+pub mod index;
+pub mod store;
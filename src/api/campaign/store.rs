@@ -0,0 +1,221 @@
+use crate::api::retry::{self, Attempt, RetryPolicy};
+use crate::api::schedule::calendar;
+use crate::config::env;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ContactState {
+    Queued,
+    Dialing,
+    Answered,
+    NoAnswer,
+    Failed,
+    Completed,
+}
+
+impl ContactState {
+    pub fn is_active(self) -> bool {
+        matches!(self, ContactState::Dialing | ContactState::Answered)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CampaignStatus {
+    Running,
+    Paused,
+    Cancelled,
+    Completed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Contact {
+    pub number: String,
+    pub name: Option<String>,
+    pub time_zone: Option<String>,
+    pub variables: Map<String, Value>,
+    pub state: ContactState,
+    pub call_id: Option<String>,
+    pub ended_reason: Option<String>,
+    pub error: Option<String>,
+    // Every call placed to the contact, oldest first.
+    pub attempts: Vec<Attempt>,
+    // When a queued contact may be dialed: after the spacing of an unanswered
+    // call, or once the calling window opens.
+    pub retry_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Campaign {
+    pub id: String,
+    pub name: Option<String>,
+    pub assistant_id: String,
+    pub phone_number_id: String,
+    pub dial_interval_ms: u64,
+    pub max_active_calls: usize,
+    pub retry: RetryPolicy,
+    // The calling window applies in this time zone, or the contact's own.
+    pub time_zone: String,
+    pub status: CampaignStatus,
+    pub created_at: String,
+    pub contacts: Vec<Contact>,
+}
+
+impl Campaign {
+    pub fn time_zone(&self, contact: &Contact) -> Tz {
+        contact
+            .time_zone
+            .as_deref()
+            .unwrap_or(&self.time_zone)
+            .parse()
+            .unwrap_or(Tz::UTC)
+    }
+
+    // Contact counts by state, for listing campaigns without their contacts.
+    pub fn summary(&self) -> Value {
+        let mut counts: HashMap<ContactState, usize> = HashMap::new();
+        for contact in &self.contacts {
+            *counts.entry(contact.state).or_insert(0) += 1;
+        }
+        json!({
+            "id": self.id,
+            "name": self.name,
+            "assistantId": self.assistant_id,
+            "phoneNumberId": self.phone_number_id,
+            "status": self.status,
+            "createdAt": self.created_at,
+            "contacts": self.contacts.len(),
+            "states": counts,
+        })
+    }
+}
+
+pub struct Campaigns {
+    pub campaigns: HashMap<String, Campaign>,
+    // Which campaign contact a call placed by the dialer belongs to, so
+    // webhook events can find it.
+    calls: HashMap<String, (String, usize)>,
+}
+
+pub fn campaigns() -> MutexGuard<'static, Campaigns> {
+    static CAMPAIGNS: OnceLock<Mutex<Campaigns>> = OnceLock::new();
+    CAMPAIGNS
+        .get_or_init(|| {
+            Mutex::new(Campaigns {
+                campaigns: HashMap::new(),
+                calls: HashMap::new(),
+            })
+        })
+        .lock()
+        .unwrap()
+}
+
+impl Campaigns {
+    pub fn dialed(&mut self, campaign_id: &str, contact: usize, call_id: String) {
+        if let Some(campaign) = self.campaigns.get_mut(campaign_id) {
+            campaign.contacts[contact].call_id = Some(call_id.clone());
+            campaign.contacts[contact].retry_at = None;
+            self.calls
+                .insert(call_id, (campaign_id.to_string(), contact));
+        }
+    }
+
+    fn contact(&mut self, call_id: &str) -> Option<&mut Contact> {
+        let (campaign_id, contact) = self.calls.get(call_id)?;
+        self.campaigns
+            .get_mut(campaign_id)
+            .map(|campaign| &mut campaign.contacts[*contact])
+    }
+}
+
+// Moves a campaign contact along as Vapi reports the status of its call.
+// Calls that are not part of a campaign are ignored.
+pub fn call_status(call_id: &str, status: &str) {
+    let mut campaigns = campaigns();
+    let contact = match campaigns.contact(call_id) {
+        Some(contact) => contact,
+        None => return,
+    };
+    contact.state = match (contact.state, status) {
+        (ContactState::Queued | ContactState::Dialing, "queued" | "ringing") => {
+            ContactState::Dialing
+        }
+        (ContactState::Queued | ContactState::Dialing, "in-progress" | "forwarding") => {
+            ContactState::Answered
+        }
+        // The end-of-call report that follows says how the call went.
+        (ContactState::Answered, "ended") => ContactState::Completed,
+        (ContactState::Queued | ContactState::Dialing, "ended") => ContactState::NoAnswer,
+        (state, _) => state,
+    };
+}
+
+// Records how the contact's call went. An unanswered call is queued again
+// while the campaign's retry policy allows, at the first time the calling
+// window permits after the spacing; the old call is forgotten so late events
+// for it can't move the contact.
+pub fn call_ended(call_id: &str, ended_reason: &str) {
+    let mut campaigns = campaigns();
+    let (campaign_id, index) = match campaigns.calls.get(call_id) {
+        Some((campaign_id, index)) => (campaign_id.clone(), *index),
+        None => return,
+    };
+    let campaign = match campaigns.campaigns.get_mut(&campaign_id) {
+        Some(campaign) => campaign,
+        None => return,
+    };
+    let contact = &mut campaign.contacts[index];
+    // Both the webhook and reconciliation report the end of a call.
+    if contact
+        .attempts
+        .iter()
+        .any(|attempt| attempt.call_id == call_id)
+    {
+        return;
+    }
+    contact.ended_reason = Some(ended_reason.to_string());
+    contact.attempts.push(Attempt {
+        call_id: call_id.to_string(),
+        ended_reason: ended_reason.to_string(),
+        ended_at: Utc::now(),
+    });
+    contact.state = match ended_reason {
+        reason if retry::is_unanswered(reason) => ContactState::NoAnswer,
+        reason if reason.contains("error") || reason.contains("failed") => ContactState::Failed,
+        _ => ContactState::Completed,
+    };
+    let tz = campaign.time_zone(&campaign.contacts[index]);
+    let contact = &mut campaign.contacts[index];
+    let retry_at = match campaign.retry.retry_at(&contact.attempts) {
+        Some(retry_at) => retry_at,
+        None => return,
+    };
+    let window = env::load_env_config().schedule.calling_window;
+    let retry_at = match calendar::next_allowed(&window, tz, retry_at) {
+        Some(retry_at) => retry_at,
+        None => {
+            contact.error = Some("the calling window allows no time to call".to_string());
+            return;
+        }
+    };
+    println!(
+        "Campaign {} will call {} again at {} ({}, attempt {} of {})",
+        campaign_id,
+        contact.number,
+        retry_at,
+        ended_reason,
+        contact.attempts.len(),
+        campaign.retry.max_attempts
+    );
+    contact.state = ContactState::Queued;
+    contact.retry_at = Some(retry_at);
+    campaigns.calls.remove(call_id);
+}
@@ -0,0 +1,294 @@
+use crate::api::custom_llm::context_window;
+use crate::api::custom_llm::normalizer::Normalizer;
+use crate::config::env::{CacheConfig, EnvConfig};
+use crate::types::vapi::CallContext;
+use actix_web::HttpResponse;
+use async_openai::types::{
+    CreateChatCompletionRequest, CreateChatCompletionResponse, CreateChatCompletionStreamResponse,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CachedAnswer {
+    key: String,
+    text: String,
+    stored_at: i64,
+}
+
+#[derive(Default)]
+struct Store {
+    entries: HashMap<String, (CachedAnswer, u64)>,
+    tick: u64,
+    hits: u64,
+    misses: u64,
+}
+
+fn store() -> &'static Mutex<Store> {
+    static STORE: OnceLock<Mutex<Store>> = OnceLock::new();
+    STORE.get_or_init(|| Mutex::new(Store::default()))
+}
+
+// A cacheable turn: everything that shapes its answer, and how long an answer
+// to it stays fresh for this assistant. Besides the normalized history, model
+// and temperature, that is who the turn is for and billed to, the tools and
+// response format on offer, and the post-processing applied before an answer
+// is stored, so orgs and assistants never share an answer they would not
+// both have been given.
+pub struct CacheKey {
+    key: String,
+    ttl_secs: i64,
+}
+
+impl CacheKey {
+    pub fn new(
+        env_config: &EnvConfig,
+        request: &CreateChatCompletionRequest,
+        context: &CallContext,
+        api_key: Option<&str>,
+        normalizer: &Normalizer,
+    ) -> Option<Self> {
+        let config = &env_config.cache;
+        if !config.enabled {
+            return None;
+        }
+        let ttl_secs = context
+            .assistant_id()
+            .and_then(|id| config.assistant_ttl_secs.get(id))
+            .copied()
+            .unwrap_or(config.ttl_secs);
+        if ttl_secs == 0 {
+            return None;
+        }
+
+        let history: Vec<String> = request
+            .messages
+            .iter()
+            .map(|message| {
+                let value = serde_json::to_value(message).unwrap_or_default();
+                format!(
+                    "{}:{}",
+                    value["role"].as_str().unwrap_or_default(),
+                    normalize(&context_window::message_text(message))
+                )
+            })
+            .collect();
+        // Only a hash of the key, so cache files never hold it.
+        let credential = api_key.map_or("server".to_string(), |key| {
+            format!("{:x}", Sha256::digest(key.as_bytes()))
+        });
+        let json = |value| serde_json::to_string(&value).unwrap_or_default();
+        let structured = context
+            .assistant_id()
+            .and_then(|id| env_config.structured_output.get(id))
+            .map(|output| json(json!([output.schema, output.speech_field])));
+        Some(CacheKey {
+            key: format!(
+                "{}|{}|{}|{}|{:?}|{}|{}|{}|{:?}|{:?}|{:?}|{}",
+                context.org_id().unwrap_or_default(),
+                context.assistant_id().unwrap_or_default(),
+                credential,
+                request.model,
+                request.temperature,
+                json(json!(request.tools)),
+                json(json!(request.tool_choice)),
+                json(json!(request.response_format)),
+                normalizer,
+                env_config.guardrails,
+                structured,
+                history.join("\n")
+            ),
+            ttl_secs: ttl_secs as i64,
+        })
+    }
+
+    pub fn get(&self, config: &CacheConfig) -> Option<String> {
+        let now = chrono::Utc::now().timestamp();
+        let mut store = store().lock().unwrap();
+        store.tick += 1;
+        let tick = store.tick;
+
+        let mut answer = store.entries.get_mut(&self.key).map(|(answer, last_used)| {
+            *last_used = tick;
+            answer.clone()
+        });
+        if answer.is_none() {
+            answer = config.dir.as_ref().and_then(|dir| self.read(dir));
+            if let Some(answer) = &answer {
+                store.insert(answer.clone(), config.capacity);
+            }
+        }
+
+        match answer {
+            Some(answer) if now - answer.stored_at <= self.ttl_secs => {
+                store.hits += 1;
+                Some(answer.text)
+            }
+            _ => {
+                store.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub fn put(&self, config: &CacheConfig, text: String) {
+        if text.trim().is_empty() {
+            return;
+        }
+        let answer = CachedAnswer {
+            key: self.key.clone(),
+            text,
+            stored_at: chrono::Utc::now().timestamp(),
+        };
+        if let Some(dir) = &config.dir {
+            if let Err(e) = fs::create_dir_all(dir)
+                .and_then(|_| fs::write(self.path(dir), serde_json::to_vec(&answer)?))
+            {
+                eprintln!("Failed to write cached answer to {}: {}", dir, e);
+            }
+        }
+        store().lock().unwrap().insert(answer, config.capacity);
+    }
+
+    fn read(&self, dir: &str) -> Option<CachedAnswer> {
+        let answer: CachedAnswer = serde_json::from_slice(&fs::read(self.path(dir)).ok()?).ok()?;
+        // File names are hashes, so make sure this is not a colliding key.
+        (answer.key == self.key).then_some(answer)
+    }
+
+    fn path(&self, dir: &str) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        self.key.hash(&mut hasher);
+        PathBuf::from(dir).join(format!("{:016x}.json", hasher.finish()))
+    }
+}
+
+impl Store {
+    fn insert(&mut self, answer: CachedAnswer, capacity: usize) {
+        self.tick += 1;
+        let tick = self.tick;
+        self.entries.insert(answer.key.clone(), (answer, tick));
+        while self.entries.len() > capacity.max(1) {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(key) => self.entries.remove(&key),
+                None => break,
+            };
+        }
+    }
+}
+
+// Turns that differ only in case, spacing or closing punctuation share an answer.
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_end_matches(['.', '!', '?'])
+        .to_lowercase()
+}
+
+// The spoken text of a finished turn, or nothing if the turn called a tool
+// and so cannot be answered from the cache next time.
+#[allow(deprecated)]
+pub fn chunks_text(chunks: &[CreateChatCompletionStreamResponse]) -> Option<String> {
+    let mut text = String::new();
+    for choice in chunks.iter().flat_map(|chunk| chunk.choices.iter()) {
+        if choice.delta.tool_calls.is_some() || choice.delta.function_call.is_some() {
+            return None;
+        }
+        text.push_str(choice.delta.content.as_deref().unwrap_or_default());
+    }
+    Some(text)
+}
+
+#[allow(deprecated)]
+pub fn response_text(response: &CreateChatCompletionResponse) -> Option<String> {
+    let message = &response.choices.first()?.message;
+    if message.tool_calls.is_some() || message.function_call.is_some() {
+        return None;
+    }
+    message.content.clone()
+}
+
+pub async fn stats() -> HttpResponse {
+    let store = store().lock().unwrap();
+    let lookups = store.hits + store.misses;
+    HttpResponse::Ok().json(json!({
+        "entries": store.entries.len(),
+        "hits": store.hits,
+        "misses": store.misses,
+        "hitRate": if lookups == 0 { 0.0 } else { store.hits as f64 / lookups as f64 },
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::custom_llm::normalizer::Locale;
+    use crate::config::env;
+    use crate::types::vapi::VapiCall;
+    use serde_json::Value;
+
+    fn key(env_config: &EnvConfig, request: Value, org_id: &str, api_key: Option<&str>) -> String {
+        let request: CreateChatCompletionRequest = serde_json::from_value(request).unwrap();
+        let context = CallContext {
+            call: Some(VapiCall {
+                org_id: Some(org_id.to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let normalizer = Normalizer {
+            enabled: true,
+            locale: Locale::American,
+        };
+        CacheKey::new(env_config, &request, &context, api_key, &normalizer)
+            .unwrap()
+            .key
+    }
+
+    #[test]
+    fn answers_are_not_shared_across_orgs_credentials_or_tools() {
+        let mut env_config = env::load_env_config();
+        env_config.cache.enabled = true;
+        env_config.cache.ttl_secs = 60;
+        let request = json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "What are your hours?"}],
+        });
+        let mut with_tools = request.clone();
+        with_tools["tools"] = json!([{
+            "type": "function",
+            "function": {"name": "lookup", "parameters": {"type": "object"}},
+        }]);
+
+        let base = key(&env_config, request.clone(), "org-a", Some("sk-a"));
+        assert_eq!(
+            base,
+            key(&env_config, request.clone(), "org-a", Some("sk-a"))
+        );
+        assert_ne!(
+            base,
+            key(&env_config, request.clone(), "org-b", Some("sk-a"))
+        );
+        assert_ne!(
+            base,
+            key(&env_config, request.clone(), "org-a", Some("sk-b"))
+        );
+        assert_ne!(base, key(&env_config, request.clone(), "org-a", None));
+        assert_ne!(base, key(&env_config, with_tools, "org-a", Some("sk-a")));
+        assert!(!base.contains("sk-a"));
+    }
+}
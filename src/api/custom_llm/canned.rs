@@ -0,0 +1,64 @@
+use crate::api::custom_llm::sse;
+use actix_web::HttpResponse;
+use async_openai::types::{
+    ChatChoice, ChatCompletionResponseMessage, CreateChatCompletionRequest,
+    CreateChatCompletionResponse, CreateChatCompletionStreamResponse, FinishReason, Role,
+};
+
+// Answers a turn with text that is already complete, in the form the request
+// asked for. `header` says where the text came from.
+pub fn respond(
+    request: &CreateChatCompletionRequest,
+    text: &str,
+    header: (&str, &str),
+) -> HttpResponse {
+    if request.stream.unwrap_or(true) {
+        let body: String = replay_chunks(text, &request.model)
+            .iter()
+            .map(sse::data)
+            .collect();
+        HttpResponse::Ok()
+            .append_header(("Content-Type", "text/event-stream"))
+            .append_header(header)
+            .body(body)
+    } else {
+        HttpResponse::Ok()
+            .append_header(header)
+            .json(response(text, &request.model))
+    }
+}
+
+// Streams the text a few words per chunk, the way a provider would send it.
+fn replay_chunks(text: &str, model: &str) -> Vec<CreateChatCompletionStreamResponse> {
+    let id = format!("canned-{}", chrono::Utc::now().timestamp_millis());
+    let words: Vec<&str> = text.split_inclusive(' ').collect();
+    let mut chunks: Vec<CreateChatCompletionStreamResponse> = words
+        .chunks(4)
+        .map(|words| sse::content_chunk(&id, model, &words.concat()))
+        .collect();
+    chunks.push(sse::text_chunk(&id, model, ""));
+    chunks
+}
+
+#[allow(deprecated)]
+pub fn response(text: &str, model: &str) -> CreateChatCompletionResponse {
+    CreateChatCompletionResponse {
+        id: format!("canned-{}", chrono::Utc::now().timestamp_millis()),
+        choices: vec![ChatChoice {
+            index: 0,
+            message: ChatCompletionResponseMessage {
+                content: Some(text.to_string()),
+                tool_calls: None,
+                role: Role::Assistant,
+                function_call: None,
+            },
+            finish_reason: Some(FinishReason::Stop),
+            logprobs: None,
+        }],
+        created: chrono::Utc::now().timestamp() as u32,
+        model: model.to_string(),
+        system_fingerprint: None,
+        object: "chat.completion".to_string(),
+        usage: None,
+    }
+}
@@ -0,0 +1,401 @@
+use crate::api::custom_llm::upstream::Upstream;
+use crate::config::env::{ContextConfig, ContextStrategy};
+use async_openai::{
+    error::OpenAIError,
+    types::{
+        ChatCompletionRequestMessage, ChatCompletionRequestMessageContentPart,
+        ChatCompletionRequestSystemMessage, ChatCompletionRequestUserMessage,
+        ChatCompletionRequestUserMessageContent, CreateChatCompletionRequest, Role,
+    },
+};
+use tiktoken_rs::{
+    cl100k_base_singleton,
+    model::get_context_size,
+    o200k_base_singleton, p50k_base_singleton, r50k_base_singleton,
+    tokenizer::{get_tokenizer, Tokenizer},
+};
+
+// Trims the conversation Vapi sent so it fits the model's context window,
+// leaving room for the completion itself.
+pub async fn fit(
+    upstream: &Upstream,
+    request: &mut CreateChatCompletionRequest,
+    config: &ContextConfig,
+) {
+    if config.strategy == ContextStrategy::None {
+        return;
+    }
+
+    let budget = config
+        .max_tokens
+        .unwrap_or_else(|| get_context_size(&request.model))
+        .saturating_sub(request.max_tokens.unwrap_or(0) as usize);
+    if count_tokens(&request.model, &request.messages) <= budget {
+        return;
+    }
+
+    match config.strategy {
+        ContextStrategy::KeepLast => keep_last(&mut request.messages, config.keep_last_turns),
+        ContextStrategy::Summarize => {
+            if let Err(e) = summarize(upstream, request, config).await {
+                eprintln!("Failed to summarize earlier turns: {}", e);
+            }
+        }
+        _ => {}
+    }
+
+    // Whatever the strategy, never forward a request that is still too long.
+    drop_oldest(&request.model, &mut request.messages, budget);
+}
+
+pub fn count_tokens(model: &str, messages: &[ChatCompletionRequestMessage]) -> usize {
+    let bpe = match get_tokenizer(model) {
+        Some(Tokenizer::O200kBase) => o200k_base_singleton(),
+        Some(Tokenizer::P50kBase) => p50k_base_singleton(),
+        Some(Tokenizer::R50kBase) | Some(Tokenizer::Gpt2) => r50k_base_singleton(),
+        _ => cl100k_base_singleton(),
+    };
+    let bpe = bpe.lock();
+
+    // Every message costs a few tokens of framing on top of its content, and
+    // every reply is primed with another three.
+    3 + messages
+        .iter()
+        .map(|message| 4 + bpe.encode_with_special_tokens(&message_text(message)).len())
+        .sum::<usize>()
+}
+
+// A turn starts with the caller speaking and runs up to their next message,
+// so it keeps the tool calls made for it together with their results.
+fn keep_last(messages: &mut Vec<ChatCompletionRequestMessage>, turns: usize) {
+    let start = last_turns_start(messages, turns);
+    let mut index = 0;
+    messages.retain(|message| {
+        index += 1;
+        is_system(message) || index > start
+    });
+}
+
+// Index of the first message of the last `turns` turns.
+fn last_turns_start(messages: &[ChatCompletionRequestMessage], turns: usize) -> usize {
+    let starts: Vec<usize> = messages
+        .iter()
+        .enumerate()
+        .filter(|(_, message)| matches!(message, ChatCompletionRequestMessage::User(_)))
+        .map(|(index, _)| index)
+        .collect();
+    match starts.len().checked_sub(turns) {
+        Some(older) if older > 0 => starts[older],
+        _ => 0,
+    }
+}
+
+fn drop_oldest(model: &str, messages: &mut Vec<ChatCompletionRequestMessage>, budget: usize) {
+    while turn_count(messages) > 1 && count_tokens(model, messages) > budget {
+        if let Some(index) = messages.iter().position(|message| !is_system(message)) {
+            messages.remove(index);
+        }
+        drop_orphaned_tool_results(messages);
+    }
+}
+
+async fn summarize(
+    upstream: &Upstream,
+    request: &mut CreateChatCompletionRequest,
+    config: &ContextConfig,
+) -> Result<(), OpenAIError> {
+    let start = last_turns_start(&request.messages, config.keep_last_turns);
+    if start == 0 {
+        return Ok(());
+    }
+
+    let transcript = request.messages[..start]
+        .iter()
+        .filter(|message| !is_system(message))
+        .map(|message| format!("{}: {}", role_name(message), message_text(message)))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let summary_request = CreateChatCompletionRequest {
+        model: config.summary_model.clone(),
+        messages: vec![
+            ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+                content: "Summarize the following phone conversation in a few sentences. Keep names, numbers and anything the assistant promised to do.".to_string(),
+                role: Role::System,
+                name: None,
+            }),
+            ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage {
+                content: ChatCompletionRequestUserMessageContent::Text(transcript),
+                role: Role::User,
+                name: None,
+            }),
+        ],
+        ..Default::default()
+    };
+    let response = upstream
+        .client_for(&config.summary_provider)
+        .chat()
+        .create(summary_request)
+        .await?;
+    let summary = response
+        .choices
+        .first()
+        .and_then(|choice| choice.message.content.clone())
+        .unwrap_or_default();
+
+    keep_last(&mut request.messages, config.keep_last_turns);
+    let position = request
+        .messages
+        .iter()
+        .position(|message| !is_system(message))
+        .unwrap_or(request.messages.len());
+    request.messages.insert(
+        position,
+        ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+            content: format!("Summary of the earlier conversation: {}", summary),
+            role: Role::System,
+            name: None,
+        }),
+    );
+    Ok(())
+}
+
+// A tool result is meaningless to the model once the assistant message that
+// requested it has been dropped.
+fn drop_orphaned_tool_results(messages: &mut Vec<ChatCompletionRequestMessage>) {
+    while let Some(index) = messages.iter().position(|message| !is_system(message)) {
+        if !matches!(messages[index], ChatCompletionRequestMessage::Tool(_)) {
+            break;
+        }
+        messages.remove(index);
+    }
+}
+
+fn turn_count(messages: &[ChatCompletionRequestMessage]) -> usize {
+    messages
+        .iter()
+        .filter(|message| !is_system(message))
+        .count()
+}
+
+fn is_system(message: &ChatCompletionRequestMessage) -> bool {
+    matches!(message, ChatCompletionRequestMessage::System(_))
+}
+
+fn role_name(message: &ChatCompletionRequestMessage) -> &'static str {
+    match message {
+        ChatCompletionRequestMessage::System(_) => "system",
+        ChatCompletionRequestMessage::User(_) => "user",
+        ChatCompletionRequestMessage::Assistant(_) => "assistant",
+        ChatCompletionRequestMessage::Tool(_) => "tool",
+        ChatCompletionRequestMessage::Function(_) => "function",
+    }
+}
+
+pub fn message_text(message: &ChatCompletionRequestMessage) -> String {
+    match message {
+        ChatCompletionRequestMessage::System(message) => message.content.clone(),
+        ChatCompletionRequestMessage::User(message) => match &message.content {
+            ChatCompletionRequestUserMessageContent::Text(text) => text.clone(),
+            ChatCompletionRequestUserMessageContent::Array(parts) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    ChatCompletionRequestMessageContentPart::Text(part) => Some(part.text.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+        },
+        ChatCompletionRequestMessage::Assistant(message) => {
+            let mut text = message.content.clone().unwrap_or_default();
+            for call in message.tool_calls.iter().flatten() {
+                text.push_str(&call.function.name);
+                text.push_str(&call.function.arguments);
+            }
+            text
+        }
+        ChatCompletionRequestMessage::Tool(message) => message.content.clone(),
+        ChatCompletionRequestMessage::Function(message) => {
+            message.content.clone().unwrap_or_default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::custom_llm::fake::{upstream, FakeLlm, Reply};
+    use crate::config::env::LlmTarget;
+    use crate::types::vapi::CustomLlmRequest;
+    use serde_json::{json, Value};
+
+    fn request(messages: Value) -> CreateChatCompletionRequest {
+        serde_json::from_value::<CustomLlmRequest>(json!({
+            "model": "gpt-4o",
+            "messages": messages,
+        }))
+        .unwrap()
+        .completion
+    }
+
+    fn config(strategy: ContextStrategy, max_tokens: usize) -> ContextConfig {
+        ContextConfig {
+            strategy,
+            max_tokens: Some(max_tokens),
+            keep_last_turns: 2,
+            summary_model: "gpt-4o-mini".to_string(),
+            summary_provider: LlmTarget {
+                provider: "summaries".to_string(),
+                base_url: "http://127.0.0.1:1".to_string(),
+                api_key: String::new(),
+                model: None,
+                max_concurrency: None,
+            },
+        }
+    }
+
+    fn conversation() -> Value {
+        json!([
+            {"role": "system", "content": "You are a receptionist."},
+            {"role": "user", "content": "What time do you open on weekdays?"},
+            {"role": "assistant", "content": null, "tool_calls": [{
+                "id": "call_1", "type": "function",
+                "function": {"name": "hours", "arguments": "{}"},
+            }]},
+            {"role": "tool", "tool_call_id": "call_1", "content": "9 to 5"},
+            {"role": "assistant", "content": "We open at nine."},
+            {"role": "user", "content": "And on Saturday?"},
+        ])
+    }
+
+    fn request_with_later_turn() -> CreateChatCompletionRequest {
+        let mut messages = conversation();
+        let turns = messages.as_array_mut().unwrap();
+        turns.insert(1, json!({"role": "user", "content": "Hi"}));
+        turns.insert(2, json!({"role": "assistant", "content": "Hello!"}));
+        turns.push(json!({"role": "assistant", "content": "Ten to two."}));
+        request(messages)
+    }
+
+    fn texts(request: &CreateChatCompletionRequest) -> Vec<String> {
+        request.messages.iter().map(message_text).collect()
+    }
+
+    #[actix_web::test]
+    async fn conversations_within_the_budget_are_left_alone() {
+        let mut request = request(conversation());
+        let before = texts(&request);
+
+        fit(
+            &upstream(Vec::new(), None),
+            &mut request,
+            &config(ContextStrategy::DropOldest, 8000),
+        )
+        .await;
+        assert_eq!(texts(&request), before);
+
+        fit(
+            &upstream(Vec::new(), None),
+            &mut request,
+            &config(ContextStrategy::None, 10),
+        )
+        .await;
+        assert_eq!(texts(&request), before);
+    }
+
+    #[actix_web::test]
+    async fn the_oldest_turns_go_first_and_never_leave_a_tool_result_behind() {
+        let mut request = request(conversation());
+        let kept = request.messages[4..].to_vec();
+        let mut expected = vec![request.messages[0].clone()];
+        expected.extend(kept);
+        let budget = count_tokens("gpt-4o", &expected);
+
+        fit(
+            &upstream(Vec::new(), None),
+            &mut request,
+            &config(ContextStrategy::DropOldest, budget),
+        )
+        .await;
+
+        assert_eq!(request.messages, expected);
+        assert!(count_tokens("gpt-4o", &request.messages) <= budget);
+    }
+
+    #[actix_web::test]
+    async fn the_latest_turn_is_kept_even_over_budget() {
+        let mut request = request(conversation());
+
+        fit(
+            &upstream(Vec::new(), None),
+            &mut request,
+            &config(ContextStrategy::DropOldest, 1),
+        )
+        .await;
+
+        assert_eq!(
+            texts(&request),
+            vec!["You are a receptionist.", "And on Saturday?"]
+        );
+    }
+
+    #[actix_web::test]
+    async fn whole_turns_are_kept_with_their_tool_calls() {
+        let mut request = request(conversation());
+        let mut config = config(ContextStrategy::KeepLast, 1);
+        config.keep_last_turns = 1;
+
+        fit(&upstream(Vec::new(), None), &mut request, &config).await;
+        assert_eq!(
+            texts(&request),
+            vec!["You are a receptionist.", "And on Saturday?"]
+        );
+
+        let mut request = request_with_later_turn();
+        config.max_tokens = Some(count_tokens("gpt-4o", &request.messages) - 1);
+        config.keep_last_turns = 2;
+
+        fit(&upstream(Vec::new(), None), &mut request, &config).await;
+        assert_eq!(
+            texts(&request)[..3],
+            [
+                "You are a receptionist.",
+                "What time do you open on weekdays?",
+                "hours{}"
+            ]
+        );
+        assert!(matches!(
+            request.messages[3],
+            ChatCompletionRequestMessage::Tool(_)
+        ));
+        assert_eq!(request.messages.len(), 7);
+    }
+
+    #[actix_web::test]
+    async fn summaries_go_to_the_summary_provider() {
+        let answering = FakeLlm::start(Vec::new());
+        let summaries = FakeLlm::start(vec![Reply::Completion(
+            "The caller asked about opening hours.".to_string(),
+        )]);
+        let upstream = upstream(
+            vec![answering.target("groq"), summaries.target("summaries")],
+            None,
+        );
+        let mut request = request(conversation());
+        let mut config = config(ContextStrategy::Summarize, 1);
+        config.keep_last_turns = 1;
+
+        fit(&upstream, &mut request, &config).await;
+
+        assert!(answering.received().is_empty());
+        assert_eq!(summaries.received()[0]["model"], "gpt-4o-mini");
+        assert_eq!(
+            texts(&request),
+            vec![
+                "You are a receptionist.",
+                "Summary of the earlier conversation: The caller asked about opening hours.",
+                "And on Saturday?",
+            ]
+        );
+    }
+}
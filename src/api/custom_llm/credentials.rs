@@ -0,0 +1,97 @@
+use crate::api::auth::keys;
+use crate::config::env::{AuthConfig, CredentialsConfig};
+use crate::types::vapi::CallContext;
+use actix_web::{http::header, HttpRequest};
+
+// The OpenAI key a request should be billed to, if it is not the server's
+// own: the bearer token Vapi forwards from the assistant's custom-LLM
+// credential when that is enabled, otherwise the key configured for the
+// call's org. The org comes from the request body, so its key is only used
+// when the request carries Vapi's secret; anyone else is served on the
+// server's own key rather than being able to bill an org by naming it.
+pub fn api_key(
+    config: &CredentialsConfig,
+    auth: &AuthConfig,
+    req: &HttpRequest,
+    context: &CallContext,
+) -> Option<String> {
+    if config.from_header {
+        let bearer = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim)
+            .filter(|key| !key.is_empty());
+        if let Some(key) = bearer {
+            println!(
+                "Using the API key from the request for call {}",
+                context.call_id().unwrap_or("unknown")
+            );
+            return Some(key.to_string());
+        }
+    }
+
+    let org_id = context.org_id()?;
+    let key = config.org_api_keys.get(org_id)?;
+    if !auth.disabled {
+        if let Err(reason) = keys::vapi(auth, req.headers()) {
+            eprintln!(
+                "Not using the API key of org {} for an unauthenticated request: {}",
+                org_id, reason
+            );
+            return None;
+        }
+    }
+    println!("Using the API key of org {}", org_id);
+    Some(key.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::vapi::VapiCall;
+    use actix_web::test::TestRequest;
+    use std::collections::HashMap;
+
+    fn auth() -> AuthConfig {
+        AuthConfig {
+            disabled: false,
+            keys: Vec::new(),
+            keys_file: String::new(),
+            jwt_secret: None,
+            webhook_secret: Some("vapi-secret".to_string()),
+            vapi_disabled: false,
+            audit_file: String::new(),
+        }
+    }
+
+    #[test]
+    fn org_keys_are_only_used_for_requests_from_vapi() {
+        let config = CredentialsConfig {
+            from_header: false,
+            org_api_keys: HashMap::from([("org-1".to_string(), "sk-org-1".to_string())]),
+        };
+        let context = CallContext {
+            call: Some(VapiCall {
+                org_id: Some("org-1".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let anyone = TestRequest::default().to_http_request();
+        assert_eq!(api_key(&config, &auth(), &anyone, &context), None);
+        let wrong = TestRequest::default()
+            .insert_header(("X-Vapi-Secret", "guess"))
+            .to_http_request();
+        assert_eq!(api_key(&config, &auth(), &wrong, &context), None);
+        let vapi = TestRequest::default()
+            .insert_header(("X-Vapi-Secret", "vapi-secret"))
+            .to_http_request();
+        assert_eq!(
+            api_key(&config, &auth(), &vapi, &context).as_deref(),
+            Some("sk-org-1")
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use actix_web::{
+    error::JsonPayloadError, http::StatusCode, HttpRequest, HttpResponse, ResponseError,
+};
+use async_openai::error::OpenAIError;
+use serde_json::{json, Value};
+use std::fmt;
+
+// Errors from the custom-LLM endpoints, reported to Vapi in the same shape
+// the OpenAI API uses so it can treat us like any other provider.
+#[derive(Debug)]
+pub enum CustomLlmError {
+    Upstream(OpenAIError),
+    InvalidRequest(String),
+    InvalidOutput(String),
+    Busy(String),
+    // Our own setup is at fault, such as an empty `LLM_FALLBACK_CHAIN`.
+    Unconfigured(String),
+}
+
+impl From<OpenAIError> for CustomLlmError {
+    fn from(error: OpenAIError) -> Self {
+        CustomLlmError::Upstream(error)
+    }
+}
+
+impl fmt::Display for CustomLlmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CustomLlmError::Upstream(e) => write!(f, "{}", e),
+            CustomLlmError::InvalidRequest(message) => write!(f, "{}", message),
+            CustomLlmError::InvalidOutput(message) => {
+                write!(f, "model output did not match the schema: {}", message)
+            }
+            CustomLlmError::Busy(message) => write!(f, "all providers are busy: {}", message),
+            CustomLlmError::Unconfigured(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl ResponseError for CustomLlmError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            CustomLlmError::Upstream(e) => upstream_status(e),
+            CustomLlmError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+            CustomLlmError::InvalidOutput(_) => StatusCode::BAD_GATEWAY,
+            CustomLlmError::Busy(_) => StatusCode::SERVICE_UNAVAILABLE,
+            CustomLlmError::Unconfigured(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(self.body())
+    }
+}
+
+impl CustomLlmError {
+    pub fn body(&self) -> Value {
+        match self {
+            CustomLlmError::Upstream(OpenAIError::ApiError(e)) => json!({
+                "error": {
+                    "message": e.message,
+                    "type": e.r#type,
+                    "param": e.param,
+                    "code": e.code,
+                }
+            }),
+            _ => {
+                let error_type = if self.status_code().is_client_error() {
+                    "invalid_request_error"
+                } else {
+                    "server_error"
+                };
+                json!({
+                    "error": {
+                        "message": self.to_string(),
+                        "type": error_type,
+                        "param": null,
+                        "code": null,
+                    }
+                })
+            }
+        }
+    }
+}
+
+fn upstream_status(error: &OpenAIError) -> StatusCode {
+    match error {
+        OpenAIError::ApiError(e) => {
+            let code = e.code.as_ref().and_then(Value::as_str);
+            match (e.r#type.as_deref(), code) {
+                (_, Some("invalid_api_key")) | (Some("authentication_error"), _) => {
+                    StatusCode::UNAUTHORIZED
+                }
+                (Some("permission_error"), _) => StatusCode::FORBIDDEN,
+                (Some("not_found_error"), _) | (_, Some("model_not_found")) => {
+                    StatusCode::NOT_FOUND
+                }
+                (_, Some("rate_limit_exceeded"))
+                | (Some("insufficient_quota"), _)
+                | (Some("rate_limit_error"), _) => StatusCode::TOO_MANY_REQUESTS,
+                (Some("invalid_request_error"), _) => StatusCode::BAD_REQUEST,
+                _ => StatusCode::BAD_GATEWAY,
+            }
+        }
+        OpenAIError::Reqwest(e) if e.is_timeout() => StatusCode::GATEWAY_TIMEOUT,
+        OpenAIError::Reqwest(e) => e
+            .status()
+            .and_then(|status| StatusCode::from_u16(status.as_u16()).ok())
+            .unwrap_or(StatusCode::BAD_GATEWAY),
+        OpenAIError::InvalidArgument(_) => StatusCode::BAD_REQUEST,
+        OpenAIError::JSONDeserialize(_) | OpenAIError::StreamError(_) => StatusCode::BAD_GATEWAY,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+// Rejects request bodies that do not parse with an OpenAI-style 400 instead of
+// actix's plain text one.
+pub fn json_error_handler(error: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    CustomLlmError::InvalidRequest(error.to_string()).into()
+}
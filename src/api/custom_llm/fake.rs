@@ -0,0 +1,125 @@
+use crate::api::custom_llm::upstream::{Target, Upstream};
+use crate::config::env::LlmTarget;
+use actix_web::rt::time::sleep;
+use actix_web::{web, App, HttpResponse, HttpServer};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// An in-process stand-in for an OpenAI compatible provider. Every request to
+// `/chat/completions` gets the next scripted reply, and the request bodies are
+// kept so tests can check what was sent. It has to be started from within an
+// actix runtime, e.g. an `#[actix_web::test]`.
+pub struct FakeLlm {
+    pub base_url: String,
+    state: Arc<State>,
+}
+
+#[derive(Default)]
+struct State {
+    replies: Mutex<VecDeque<Reply>>,
+    received: Mutex<Vec<Value>>,
+}
+
+pub enum Reply {
+    // Server-sent chunks, the first of them only after the delay.
+    Stream(Duration, Vec<Value>),
+    // A whole completion answering with the text.
+    Completion(String),
+    Status(u16),
+}
+
+impl FakeLlm {
+    pub fn start(replies: Vec<Reply>) -> Self {
+        let state = Arc::new(State {
+            replies: Mutex::new(replies.into()),
+            ..Default::default()
+        });
+        let data = web::Data::from(state.clone());
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(data.clone())
+                .route("/chat/completions", web::post().to(reply))
+        })
+        .workers(1)
+        .disable_signals()
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let base_url = format!("http://{}", server.addrs()[0]);
+        actix_web::rt::spawn(server.run());
+        FakeLlm { base_url, state }
+    }
+
+    pub fn target(&self, provider: &str) -> Target {
+        Target::from_config(&LlmTarget {
+            provider: provider.to_string(),
+            base_url: self.base_url.clone(),
+            api_key: "test".to_string(),
+            model: None,
+            max_concurrency: None,
+        })
+    }
+
+    pub fn received(&self) -> Vec<Value> {
+        self.state.received.lock().unwrap().clone()
+    }
+}
+
+async fn reply(state: web::Data<State>, body: web::Json<Value>) -> HttpResponse {
+    state.received.lock().unwrap().push(body.into_inner());
+    let next = state.replies.lock().unwrap().pop_front();
+    match next {
+        Some(Reply::Stream(delay, chunks)) => {
+            sleep(delay).await;
+            let mut events: String = chunks
+                .iter()
+                .map(|chunk| format!("data: {}\n\n", chunk))
+                .collect();
+            events.push_str("data: [DONE]\n\n");
+            HttpResponse::Ok()
+                .content_type("text/event-stream")
+                .body(events)
+        }
+        Some(Reply::Completion(text)) => HttpResponse::Ok().json(json!({
+            "id": "chatcmpl-fake",
+            "object": "chat.completion",
+            "created": 0,
+            "model": "fake",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": text},
+                "finish_reason": "stop",
+            }],
+        })),
+        Some(Reply::Status(status)) => {
+            HttpResponse::build(actix_web::http::StatusCode::from_u16(status).unwrap())
+                .json(json!({"error": {"message": "scripted failure", "type": "server_error"}}))
+        }
+        None => HttpResponse::InternalServerError()
+            .json(json!({"error": {"message": "no reply scripted", "type": "server_error"}})),
+    }
+}
+
+// One streamed chunk carrying `delta`.
+pub fn chunk(delta: Value, finish_reason: Option<&str>) -> Value {
+    json!({
+        "id": "chatcmpl-fake",
+        "object": "chat.completion.chunk",
+        "created": 0,
+        "model": "fake",
+        "choices": [{"index": 0, "delta": delta, "finish_reason": finish_reason}],
+    })
+}
+
+// A chain of the given targets that waits long enough for a first token.
+pub fn upstream(targets: Vec<Target>, hedge_delay: Option<Duration>) -> Upstream {
+    Upstream {
+        targets,
+        first_token_timeout: Duration::from_secs(5),
+        hedge_delay,
+        queue_limit: 0,
+        queue_timeout: Duration::ZERO,
+        rate_limit_cooldown: Duration::ZERO,
+    }
+}
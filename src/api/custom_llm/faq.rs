@@ -0,0 +1,221 @@
+use crate::api::custom_llm::context_window;
+use crate::config::env::FaqConfig;
+use async_openai::types::{ChatCompletionRequestMessage, CreateChatCompletionRequest};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "can", "do", "does", "i", "is", "it", "me", "my", "of", "on", "the",
+    "to", "we", "you", "your",
+];
+
+// One entry of the FAQ file: [{"question": "...", "answer": "..."}]
+#[derive(Deserialize)]
+struct FaqEntry {
+    question: String,
+    answer: String,
+}
+
+type LoadedIndexes = HashMap<String, (SystemTime, Arc<FaqIndex>)>;
+
+// TF-IDF vectors of the FAQ questions.
+struct FaqIndex {
+    entries: Vec<FaqEntry>,
+    vectors: Vec<HashMap<String, f64>>,
+    document_frequency: HashMap<String, usize>,
+}
+
+impl FaqIndex {
+    fn new(entries: Vec<FaqEntry>) -> Self {
+        let terms: Vec<Vec<String>> = entries
+            .iter()
+            .map(|entry| tokenize(&entry.question))
+            .collect();
+        let mut document_frequency = HashMap::new();
+        for question in &terms {
+            let mut seen: Vec<&String> = question.iter().collect();
+            seen.sort();
+            seen.dedup();
+            for term in seen {
+                *document_frequency.entry(term.clone()).or_insert(0) += 1;
+            }
+        }
+
+        let mut index = FaqIndex {
+            entries,
+            vectors: Vec::new(),
+            document_frequency,
+        };
+        index.vectors = terms.iter().map(|terms| index.vector(terms)).collect();
+        index
+    }
+
+    // Smoothed IDF, so terms no question uses still count against a match.
+    fn vector(&self, terms: &[String]) -> HashMap<String, f64> {
+        let documents = self.entries.len() as f64;
+        let mut vector: HashMap<String, f64> = HashMap::new();
+        for term in terms {
+            *vector.entry(term.clone()).or_insert(0.0) += 1.0;
+        }
+        for (term, weight) in vector.iter_mut() {
+            let frequency = self.document_frequency.get(term).copied().unwrap_or(0) as f64;
+            *weight *= ((1.0 + documents) / (1.0 + frequency)).ln() + 1.0;
+        }
+        let norm = vector
+            .values()
+            .map(|weight| weight * weight)
+            .sum::<f64>()
+            .sqrt();
+        if norm > 0.0 {
+            vector.values_mut().for_each(|weight| *weight /= norm);
+        }
+        vector
+    }
+
+    fn best_match(&self, utterance: &str) -> Option<(&FaqEntry, f64)> {
+        let query = self.vector(&tokenize(utterance));
+        self.vectors
+            .iter()
+            .map(|vector| {
+                query
+                    .iter()
+                    .filter_map(|(term, weight)| vector.get(term).map(|other| weight * other))
+                    .sum::<f64>()
+            })
+            .zip(&self.entries)
+            .map(|(score, entry)| (entry, score))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+    }
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty() && !STOP_WORDS.contains(word))
+        .map(|word| word.to_string())
+        .collect()
+}
+
+// The index is built once per FAQ file and rebuilt when the file changes.
+fn load_index(path: &str) -> Option<Arc<FaqIndex>> {
+    static INDEXES: OnceLock<Mutex<LoadedIndexes>> = OnceLock::new();
+    let modified = fs::metadata(path).and_then(|meta| meta.modified()).ok()?;
+
+    let mut indexes = INDEXES
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap();
+    if let Some((loaded, index)) = indexes.get(path) {
+        if *loaded == modified {
+            return Some(index.clone());
+        }
+    }
+
+    let entries: Vec<FaqEntry> = match fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| serde_json::from_str(&text).map_err(|e| e.to_string()))
+    {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("Failed to load FAQ file {}: {}", path, e);
+            return None;
+        }
+    };
+    let index = Arc::new(FaqIndex::new(entries));
+    indexes.insert(path.to_string(), (modified, index.clone()));
+    Some(index)
+}
+
+// The canned answer for the caller's latest utterance, if it is close enough
+// to one of the FAQ questions. Only turns that end with the caller speaking
+// are matched, never ones continuing after a tool result.
+pub fn answer(config: &FaqConfig, request: &CreateChatCompletionRequest) -> Option<String> {
+    let index = load_index(config.file.as_deref()?)?;
+    let utterance = match request.messages.last()? {
+        message @ ChatCompletionRequestMessage::User(_) => context_window::message_text(message),
+        _ => return None,
+    };
+
+    let (entry, score) = index.best_match(&utterance)?;
+    if score < config.threshold {
+        return None;
+    }
+    println!(
+        "FAQ match {:.2} for \"{}\": {}",
+        score, utterance, entry.question
+    );
+    Some(entry.answer.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::vapi::CustomLlmRequest;
+    use serde_json::{json, Value};
+    use std::env;
+
+    fn faq_file() -> String {
+        let path = env::temp_dir().join(format!("faq-{}.json", std::process::id()));
+        let entries = json!([
+            {"question": "What are your opening hours?", "answer": "Nine to five."},
+            {"question": "Where is your office located?", "answer": "On Main Street."},
+            {"question": "Do you accept credit cards?", "answer": "Yes, all of them."},
+        ]);
+        fs::write(&path, entries.to_string()).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    fn request(messages: Value) -> CreateChatCompletionRequest {
+        serde_json::from_value::<CustomLlmRequest>(json!({
+            "model": "gpt-4o",
+            "messages": messages,
+        }))
+        .unwrap()
+        .completion
+    }
+
+    fn ask(config: &FaqConfig, utterance: &str) -> Option<String> {
+        answer(
+            config,
+            &request(json!([{"role": "user", "content": utterance}])),
+        )
+    }
+
+    #[test]
+    fn only_close_enough_questions_are_answered() {
+        let mut config = FaqConfig {
+            file: Some(faq_file()),
+            threshold: 0.5,
+        };
+
+        assert_eq!(
+            ask(&config, "What are your opening hours?").as_deref(),
+            Some("Nine to five.")
+        );
+        assert_eq!(
+            ask(&config, "opening hours today").as_deref(),
+            Some("Nine to five.")
+        );
+        assert_eq!(ask(&config, "Can I book a table for two?"), None);
+
+        config.threshold = 0.95;
+        assert_eq!(ask(&config, "opening hours today"), None);
+        assert_eq!(
+            ask(&config, "What are your opening hours").as_deref(),
+            Some("Nine to five.")
+        );
+
+        let after_tool = request(json!([
+            {"role": "user", "content": "What are your opening hours?"},
+            {"role": "assistant", "content": null, "tool_calls": [{
+                "id": "call_1", "type": "function",
+                "function": {"name": "hours", "arguments": "{}"},
+            }]},
+            {"role": "tool", "tool_call_id": "call_1", "content": "9 to 5"},
+        ]));
+        assert_eq!(answer(&config, &after_tool), None);
+    }
+}
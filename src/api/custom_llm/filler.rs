@@ -0,0 +1,239 @@
+use crate::api::custom_llm::{context_window, error::CustomLlmError, sse};
+use crate::config::env::FillerConfig;
+use actix_web::{rt::time::sleep, web::Bytes, HttpResponse};
+use async_openai::types::{ChatCompletionRequestMessage, CreateChatCompletionRequest};
+use futures::future::{self, select, Either};
+use futures::stream::{self, StreamExt};
+use rand::seq::SliceRandom;
+use regex::Regex;
+use std::future::Future;
+use std::time::Duration;
+
+pub struct Filler {
+    delay: Duration,
+    phrase: String,
+}
+
+// Picks a filler for the intent of the caller's last turn. Phrases the
+// assistant has already said in this call are skipped, so the caller never
+// hears the same one twice.
+pub fn choose(config: &FillerConfig, request: &CreateChatCompletionRequest) -> Option<Filler> {
+    let delay = Duration::from_millis(config.delay_ms?);
+
+    let last_user_turn = request
+        .messages
+        .iter()
+        .rev()
+        .find(|message| matches!(message, ChatCompletionRequestMessage::User(_)))
+        .map(context_window::message_text)
+        .unwrap_or_default();
+    let phrases = config
+        .intents
+        .iter()
+        .find(|intent| mentions_any(&last_user_turn, &intent.keywords))
+        .map(|intent| &intent.phrases)
+        .unwrap_or(&config.default_phrases);
+
+    let said: Vec<String> = request
+        .messages
+        .iter()
+        .filter(|message| matches!(message, ChatCompletionRequestMessage::Assistant(_)))
+        .map(context_window::message_text)
+        .collect();
+    let unused: Vec<&String> = phrases
+        .iter()
+        .filter(|phrase| !said.iter().any(|text| text.contains(phrase.as_str())))
+        .collect();
+
+    unused.choose(&mut rand::thread_rng()).map(|phrase| Filler {
+        delay,
+        phrase: phrase.to_string(),
+    })
+}
+
+fn mentions_any(text: &str, keywords: &[String]) -> bool {
+    if keywords.is_empty() {
+        return false;
+    }
+    let alternatives: Vec<String> = keywords
+        .iter()
+        .map(|keyword| regex::escape(keyword))
+        .collect();
+    Regex::new(&format!(r"(?i)\b(?:{})\b", alternatives.join("|")))
+        .map(|regex| regex.is_match(text))
+        .unwrap_or(false)
+}
+
+// Sends a streamed turn to Vapi. If the turn is not ready by the time the
+// filler's delay runs out, the filler goes out straight away and the turn
+// follows it. The filler carries no finish reason, so Vapi treats it and the
+// answer as one reply, and it is sent at most once.
+pub async fn respond<F>(
+    turn: F,
+    filler: Option<Filler>,
+    call_id: &str,
+    model: &str,
+    apology: &str,
+) -> Result<HttpResponse, CustomLlmError>
+where
+    F: Future<Output = Result<(String, String), CustomLlmError>> + 'static,
+{
+    let turn = Box::pin(turn);
+    let (filler, turn) = match filler {
+        Some(filler) => match select(turn, Box::pin(sleep(filler.delay))).await {
+            Either::Left((result, _)) => return result.map(event_stream),
+            Either::Right((_, turn)) => (filler, turn),
+        },
+        None => return turn.await.map(event_stream),
+    };
+
+    println!("Sending filler for call {}: {}", call_id, filler.phrase);
+    let id = format!("filler-{}", call_id);
+    let first = sse::data(&sse::content_chunk(
+        &id,
+        model,
+        &format!("{} ", filler.phrase),
+    ));
+    let (call_id, model, apology) = (call_id.to_string(), model.to_string(), apology.to_string());
+    let rest = async move {
+        match turn.await {
+            Ok((_, body)) => body,
+            Err(e) => {
+                eprintln!("Turn for call {} failed after filler: {}", call_id, e);
+                format!(
+                    "{}{}",
+                    sse::error_event(&e),
+                    sse::data(&sse::text_chunk(&id, &model, &apology))
+                )
+            }
+        }
+    };
+
+    let body = stream::once(future::ready(first))
+        .chain(stream::once(rest))
+        .map(|text| Ok::<_, actix_web::Error>(Bytes::from(text)));
+    Ok(HttpResponse::Ok()
+        .append_header(("Content-Type", "text/event-stream"))
+        .streaming(body))
+}
+
+fn event_stream((provider, body): (String, String)) -> HttpResponse {
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "text/event-stream"))
+        .append_header(("X-LLM-Provider", provider))
+        .body(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::env::FillerIntent;
+    use crate::types::vapi::CustomLlmRequest;
+    use actix_web::body::to_bytes;
+    use serde_json::{json, Value};
+
+    fn config(delay_ms: Option<u64>) -> FillerConfig {
+        FillerConfig {
+            delay_ms,
+            intents: vec![FillerIntent {
+                intent: "lookup".to_string(),
+                keywords: vec!["order".to_string()],
+                phrases: vec!["Let me check.".to_string(), "One moment.".to_string()],
+            }],
+            default_phrases: vec!["Hmm.".to_string()],
+        }
+    }
+
+    fn request(messages: Value) -> CreateChatCompletionRequest {
+        serde_json::from_value::<CustomLlmRequest>(json!({
+            "model": "gpt-4o",
+            "messages": messages,
+        }))
+        .unwrap()
+        .completion
+    }
+
+    fn phrase(filler: Option<Filler>) -> Option<String> {
+        filler.map(|filler| filler.phrase)
+    }
+
+    async fn body(response: HttpResponse) -> String {
+        String::from_utf8(to_bytes(response.into_body()).await.unwrap().to_vec()).unwrap()
+    }
+
+    fn filler(delay_ms: u64) -> Option<Filler> {
+        Some(Filler {
+            delay: Duration::from_millis(delay_ms),
+            phrase: "One moment.".to_string(),
+        })
+    }
+
+    #[test]
+    fn fillers_match_the_intent_and_are_not_repeated() {
+        let order = request(json!([
+            {"role": "user", "content": "Where is my order?"},
+            {"role": "assistant", "content": "Let me check. It shipped."},
+            {"role": "user", "content": "And the other order?"},
+        ]));
+        assert_eq!(
+            phrase(choose(&config(Some(300)), &order)).as_deref(),
+            Some("One moment.")
+        );
+
+        let chat = request(json!([{"role": "user", "content": "Hello there"}]));
+        assert_eq!(
+            phrase(choose(&config(Some(300)), &chat)).as_deref(),
+            Some("Hmm.")
+        );
+        assert_eq!(phrase(choose(&config(None), &chat)), None);
+    }
+
+    #[actix_web::test]
+    async fn fillers_only_go_out_when_the_turn_is_slow() {
+        let fast = respond(
+            async { Ok(("openai/gpt-4o".to_string(), "data: answer\n\n".to_string())) },
+            filler(200),
+            "call-1",
+            "gpt-4o",
+            "Sorry.",
+        )
+        .await
+        .unwrap();
+        assert_eq!(body(fast).await, "data: answer\n\n");
+
+        let slow = respond(
+            async {
+                sleep(Duration::from_millis(100)).await;
+                Ok(("openai/gpt-4o".to_string(), "data: answer\n\n".to_string()))
+            },
+            filler(10),
+            "call-1",
+            "gpt-4o",
+            "Sorry.",
+        )
+        .await
+        .unwrap();
+        let text = body(slow).await;
+        let filler_at = text.find("One moment. ").unwrap();
+        assert!(filler_at < text.find("data: answer").unwrap());
+    }
+
+    #[actix_web::test]
+    async fn turns_failing_after_the_filler_end_in_the_apology() {
+        let failed = respond(
+            async {
+                sleep(Duration::from_millis(50)).await;
+                Err(CustomLlmError::Busy("no slot".to_string()))
+            },
+            filler(10),
+            "call-1",
+            "gpt-4o",
+            "Sorry, say that again?",
+        )
+        .await
+        .unwrap();
+        let text = body(failed).await;
+        assert!(text.contains("One moment. "));
+        assert!(text.contains("Sorry, say that again?"));
+    }
+}
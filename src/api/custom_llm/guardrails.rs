@@ -0,0 +1,266 @@
+use crate::config::env::{GuardrailAction, GuardrailsConfig};
+use async_openai::types::{CreateChatCompletionResponse, CreateChatCompletionStreamResponse};
+use regex::Regex;
+
+struct Rule {
+    regex: Regex,
+    action: GuardrailAction,
+    replacement: String,
+}
+
+pub struct Guardrails {
+    rules: Vec<Rule>,
+    fallback: String,
+    hold_chars: usize,
+}
+
+impl Guardrails {
+    pub fn from_config(config: &GuardrailsConfig) -> Self {
+        let mut rules = Vec::new();
+        let mut hold_chars = config.hold_chars;
+        for rule in config.rules.iter().filter(|rule| !rule.patterns.is_empty()) {
+            let patterns = if rule.literal {
+                // A phrase longer than the hold-back window could otherwise be
+                // half spoken before the rest of it arrives.
+                let longest = rule.patterns.iter().map(|pattern| pattern.len()).max();
+                hold_chars = hold_chars.max(longest.unwrap_or(0));
+                let alternatives: Vec<String> = rule
+                    .patterns
+                    .iter()
+                    .map(|pattern| regex::escape(pattern))
+                    .collect();
+                vec![format!(r"(?i)\b(?:{})\b", alternatives.join("|"))]
+            } else {
+                rule.patterns.clone()
+            };
+            for pattern in patterns {
+                match Regex::new(&pattern) {
+                    // It would match between every two characters.
+                    Ok(regex) if regex.is_match("") => {
+                        eprintln!(
+                            "Skipping guardrail pattern {} that matches empty text",
+                            pattern
+                        )
+                    }
+                    Ok(regex) => rules.push(Rule {
+                        regex,
+                        action: rule.action,
+                        replacement: rule.replacement.clone(),
+                    }),
+                    Err(e) => eprintln!("Skipping invalid guardrail pattern {}: {}", pattern, e),
+                }
+            }
+        }
+        Guardrails {
+            rules,
+            fallback: config.fallback.clone(),
+            hold_chars,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+// Filters the text of one response as it streams in. A few characters are
+// held back after every delta so that a match spanning two chunks is still
+// caught before any of it is spoken.
+pub struct GuardrailFilter<'a> {
+    guardrails: &'a Guardrails,
+    pending: String,
+    stopped: bool,
+}
+
+impl<'a> GuardrailFilter<'a> {
+    pub fn new(guardrails: &'a Guardrails) -> Self {
+        GuardrailFilter {
+            guardrails,
+            pending: String::new(),
+            stopped: false,
+        }
+    }
+
+    pub fn push(&mut self, delta: &str) -> String {
+        if self.stopped {
+            return String::new();
+        }
+        self.pending.push_str(delta);
+        self.drain(false)
+    }
+
+    pub fn finish(&mut self) -> String {
+        if self.stopped {
+            return String::new();
+        }
+        self.drain(true)
+    }
+
+    fn drain(&mut self, last: bool) -> String {
+        let guardrails = self.guardrails;
+        let mut output = String::new();
+        loop {
+            let found = guardrails
+                .rules
+                .iter()
+                .filter_map(|rule| {
+                    // Empty matches, such as `\b`, would never let the
+                    // buffer move on.
+                    rule.regex
+                        .find_iter(&self.pending)
+                        .find(|m| !m.is_empty())
+                        .map(|m| (m.start(), m.end(), rule))
+                })
+                .min_by_key(|(start, _, _)| *start);
+
+            match found {
+                Some((start, end, rule)) if last || end < self.pending.len() => {
+                    match rule.action {
+                        GuardrailAction::Replace => {
+                            output.push_str(&self.pending[..start]);
+                            output.push_str(&rule.replacement);
+                            self.pending.drain(..end);
+                        }
+                        GuardrailAction::CutOff => {
+                            output.push_str(&self.pending[..start]);
+                            self.stop();
+                            return output;
+                        }
+                        GuardrailAction::Fallback => {
+                            // Drop the whole offending sentence, not just the match.
+                            let sentence_start = self.pending[..start]
+                                .rfind(['.', '!', '?'])
+                                .map(|index| index + 1)
+                                .unwrap_or(0);
+                            output.push_str(&self.pending[..sentence_start]);
+                            if !output.is_empty() {
+                                output.push(' ');
+                            }
+                            output.push_str(&guardrails.fallback);
+                            self.stop();
+                            return output;
+                        }
+                    }
+                }
+                Some((start, _, _)) => {
+                    // The match runs to the end of the buffer and may still grow.
+                    output.push_str(&self.pending[..start]);
+                    self.pending.drain(..start);
+                    return output;
+                }
+                None => {
+                    let safe = if last {
+                        self.pending.len()
+                    } else {
+                        floor_char_boundary(
+                            &self.pending,
+                            self.pending.len().saturating_sub(guardrails.hold_chars),
+                        )
+                    };
+                    output.push_str(&self.pending[..safe]);
+                    self.pending.drain(..safe);
+                    return output;
+                }
+            }
+        }
+    }
+
+    fn stop(&mut self) {
+        self.stopped = true;
+        self.pending.clear();
+    }
+}
+
+pub fn filter_chunks(guardrails: &Guardrails, chunks: &mut [CreateChatCompletionStreamResponse]) {
+    if guardrails.is_empty() {
+        return;
+    }
+
+    let mut filter = GuardrailFilter::new(guardrails);
+    let mut flushed = false;
+    for chunk in chunks.iter_mut() {
+        for choice in chunk.choices.iter_mut() {
+            let mut output = match &choice.delta.content {
+                Some(content) => filter.push(content),
+                None => String::new(),
+            };
+            if choice.finish_reason.is_some() {
+                output.push_str(&filter.finish());
+                flushed = true;
+            }
+            if choice.delta.content.is_some() || !output.is_empty() {
+                choice.delta.content = Some(output);
+            }
+        }
+    }
+
+    // Streams that end without a finish reason still need their tail.
+    if !flushed {
+        let rest = filter.finish();
+        if let Some(choice) = chunks
+            .last_mut()
+            .and_then(|chunk| chunk.choices.first_mut())
+        {
+            choice
+                .delta
+                .content
+                .get_or_insert_with(String::new)
+                .push_str(&rest);
+        }
+    }
+}
+
+pub fn filter_response(guardrails: &Guardrails, response: &mut CreateChatCompletionResponse) {
+    if guardrails.is_empty() {
+        return;
+    }
+
+    for choice in response.choices.iter_mut() {
+        if let Some(content) = &choice.message.content {
+            let mut filter = GuardrailFilter::new(guardrails);
+            let mut output = filter.push(content);
+            output.push_str(&filter.finish());
+            choice.message.content = Some(output);
+        }
+    }
+}
+
+fn floor_char_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::env::GuardrailRuleConfig;
+
+    fn guardrails(patterns: &[&str]) -> Guardrails {
+        Guardrails::from_config(&GuardrailsConfig {
+            rules: vec![GuardrailRuleConfig {
+                patterns: patterns.iter().map(|pattern| pattern.to_string()).collect(),
+                literal: false,
+                action: GuardrailAction::Replace,
+                replacement: "#".to_string(),
+            }],
+            fallback: String::new(),
+            hold_chars: 4,
+        })
+    }
+
+    #[test]
+    fn patterns_that_match_empty_text_do_not_stall_the_filter() {
+        let guardrails = guardrails(&[r"\d*", r"\bpin\b|\b", r"\d{4}"]);
+        // `\d*` is refused when loaded; the empty half of the second is skipped.
+        assert_eq!(guardrails.rules.len(), 2);
+
+        let mut filter = GuardrailFilter::new(&guardrails);
+        let mut output = filter.push("Your pin is ");
+        output.push_str(&filter.push("1234, "));
+        output.push_str(&filter.push("thanks."));
+        output.push_str(&filter.finish());
+        assert_eq!(output, "Your # is #, thanks.");
+    }
+}
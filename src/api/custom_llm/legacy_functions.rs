@@ -0,0 +1,246 @@
+use async_openai::types::{
+    ChatCompletionFunctionCall, ChatCompletionMessageToolCall, ChatCompletionNamedToolChoice,
+    ChatCompletionRequestMessage, ChatCompletionRequestToolMessage, ChatCompletionTool,
+    ChatCompletionToolChoiceOption, ChatCompletionToolType, CreateChatCompletionRequest,
+    CreateChatCompletionResponse, CreateChatCompletionStreamResponse, FinishReason,
+    FunctionCallStream, FunctionName, FunctionObject, Role,
+};
+
+// Rewrites a request that uses the legacy `functions`/`function_call` shape,
+// as older Vapi assistants send it, into `tools`/`tool_choice`, including the
+// function calls and results already in the history. Returns whether the
+// request was legacy, in which case the answer has to be converted back.
+#[allow(deprecated)]
+pub fn to_tools(request: &mut CreateChatCompletionRequest) -> bool {
+    let legacy = request.functions.is_some() || request.function_call.is_some();
+
+    if let Some(functions) = request.functions.take() {
+        if request.tools.is_none() {
+            request.tools = Some(
+                functions
+                    .into_iter()
+                    .map(|function| ChatCompletionTool {
+                        r#type: ChatCompletionToolType::Function,
+                        function: FunctionObject {
+                            name: function.name,
+                            description: function.description,
+                            parameters: Some(function.parameters),
+                        },
+                    })
+                    .collect(),
+            );
+        }
+    }
+    if let Some(function_call) = request.function_call.take() {
+        if request.tool_choice.is_none() {
+            request.tool_choice = Some(match function_call {
+                ChatCompletionFunctionCall::None => ChatCompletionToolChoiceOption::None,
+                ChatCompletionFunctionCall::Auto => ChatCompletionToolChoiceOption::Auto,
+                ChatCompletionFunctionCall::Function { name } => {
+                    ChatCompletionToolChoiceOption::Named(ChatCompletionNamedToolChoice {
+                        r#type: ChatCompletionToolType::Function,
+                        function: FunctionName { name },
+                    })
+                }
+            });
+        }
+    }
+
+    // Legacy calls have no ids, so each one gets an id that its result, the
+    // next function message with the same name, then refers to.
+    let mut pending: Vec<(String, String)> = Vec::new();
+    for (index, message) in request.messages.iter_mut().enumerate() {
+        match message {
+            ChatCompletionRequestMessage::Assistant(message) => {
+                if let Some(call) = message.function_call.take() {
+                    let id = format!("call_legacy_{}", index);
+                    pending.push((call.name.clone(), id.clone()));
+                    message.tool_calls = Some(vec![ChatCompletionMessageToolCall {
+                        id,
+                        r#type: ChatCompletionToolType::Function,
+                        function: call,
+                    }]);
+                }
+            }
+            ChatCompletionRequestMessage::Function(function) => {
+                let position = pending.iter().position(|(name, _)| *name == function.name);
+                if let Some((_, id)) = position.map(|position| pending.remove(position)) {
+                    *message =
+                        ChatCompletionRequestMessage::Tool(ChatCompletionRequestToolMessage {
+                            role: Role::Tool,
+                            content: function.content.take().unwrap_or_default(),
+                            tool_call_id: id,
+                        });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    legacy
+}
+
+// Turns streamed tool calls back into a function call. The legacy shape has
+// room for one call per turn, so any further calls are dropped.
+#[allow(deprecated)]
+pub fn to_function_chunks(chunks: &mut [CreateChatCompletionStreamResponse]) {
+    for choice in chunks.iter_mut().flat_map(|chunk| chunk.choices.iter_mut()) {
+        if let Some(calls) = choice.delta.tool_calls.take() {
+            if calls.iter().any(|call| call.index > 0) {
+                eprintln!("Dropping parallel tool calls a legacy function request cannot express");
+            }
+            choice.delta.function_call = calls
+                .into_iter()
+                .find(|call| call.index == 0)
+                .and_then(|call| call.function)
+                .map(|function| FunctionCallStream {
+                    name: function.name,
+                    arguments: function.arguments,
+                });
+        }
+        if choice.finish_reason == Some(FinishReason::ToolCalls) {
+            choice.finish_reason = Some(FinishReason::FunctionCall);
+        }
+    }
+}
+
+#[allow(deprecated)]
+pub fn to_function_response(response: &mut CreateChatCompletionResponse) {
+    for choice in response.choices.iter_mut() {
+        if let Some(calls) = choice.message.tool_calls.take() {
+            if calls.len() > 1 {
+                eprintln!("Dropping parallel tool calls a legacy function request cannot express");
+            }
+            choice.message.function_call = calls.into_iter().next().map(|call| call.function);
+        }
+        if choice.finish_reason == Some(FinishReason::ToolCalls) {
+            choice.finish_reason = Some(FinishReason::FunctionCall);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::vapi::CustomLlmRequest;
+    use serde_json::json;
+
+    #[test]
+    fn legacy_requests_and_their_history_become_tools() {
+        let mut request = serde_json::from_value::<CustomLlmRequest>(json!({
+            "model": "gpt-4o",
+            "messages": [
+                {"role": "user", "content": "Weather in Oslo?"},
+                {"role": "assistant", "content": null,
+                 "function_call": {"name": "weather", "arguments": "{\"city\":\"Oslo\"}"}},
+                {"role": "function", "name": "weather", "content": "Rain"},
+                {"role": "user", "content": "And tomorrow?"},
+            ],
+            "functions": [{"name": "weather", "parameters": {"type": "object"}}],
+            "function_call": {"name": "weather"},
+        }))
+        .unwrap()
+        .completion;
+
+        assert!(to_tools(&mut request));
+
+        let request = serde_json::to_value(&request).unwrap();
+        assert!(request.get("functions").is_none());
+        assert!(request.get("function_call").is_none());
+        assert_eq!(request["tools"][0]["type"], "function");
+        assert_eq!(request["tools"][0]["function"]["name"], "weather");
+        assert_eq!(request["tool_choice"]["function"]["name"], "weather");
+        let call = &request["messages"][1]["tool_calls"][0];
+        assert_eq!(call["id"], "call_legacy_1");
+        assert_eq!(call["function"]["arguments"], "{\"city\":\"Oslo\"}");
+        assert_eq!(request["messages"][2]["role"], "tool");
+        assert_eq!(request["messages"][2]["tool_call_id"], "call_legacy_1");
+        assert_eq!(request["messages"][2]["content"], "Rain");
+
+        let mut current: CreateChatCompletionRequest = serde_json::from_value(json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "Hi"}],
+        }))
+        .unwrap();
+        assert!(!to_tools(&mut current));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn tool_calls_go_back_as_a_single_function_call() {
+        let mut response: CreateChatCompletionResponse = serde_json::from_value(json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 0,
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "finish_reason": "tool_calls",
+                "message": {"role": "assistant", "content": null, "tool_calls": [
+                    {"id": "call_1", "type": "function",
+                     "function": {"name": "weather", "arguments": "{}"}},
+                    {"id": "call_2", "type": "function",
+                     "function": {"name": "news", "arguments": "{}"}},
+                ]},
+            }],
+        }))
+        .unwrap();
+        to_function_response(&mut response);
+        let choice = &response.choices[0];
+        assert!(choice.message.tool_calls.is_none());
+        assert_eq!(
+            choice.message.function_call.as_ref().unwrap().name,
+            "weather"
+        );
+        assert_eq!(choice.finish_reason, Some(FinishReason::FunctionCall));
+
+        let chunk = |delta: serde_json::Value, finish_reason: Option<&str>| {
+            serde_json::from_value::<CreateChatCompletionStreamResponse>(json!({
+                "id": "chatcmpl-1",
+                "object": "chat.completion.chunk",
+                "created": 0,
+                "model": "gpt-4o",
+                "choices": [{"index": 0, "delta": delta, "finish_reason": finish_reason}],
+            }))
+            .unwrap()
+        };
+        let mut chunks = vec![
+            chunk(
+                json!({"tool_calls": [
+                    {"index": 0, "id": "call_1", "type": "function",
+                     "function": {"name": "weather", "arguments": ""}},
+                    {"index": 1, "id": "call_2", "type": "function",
+                     "function": {"name": "news", "arguments": ""}},
+                ]}),
+                None,
+            ),
+            chunk(
+                json!({"tool_calls": [{"index": 0, "function": {"arguments": "{}"}}]}),
+                None,
+            ),
+            chunk(json!({}), Some("tool_calls")),
+        ];
+        to_function_chunks(&mut chunks);
+        let calls: Vec<(Option<String>, Option<String>)> = chunks[..2]
+            .iter()
+            .map(|chunk| {
+                let call = chunk.choices[0].delta.function_call.clone().unwrap();
+                (call.name, call.arguments)
+            })
+            .collect();
+        assert_eq!(
+            calls,
+            vec![
+                (Some("weather".to_string()), Some(String::new())),
+                (None, Some("{}".to_string())),
+            ]
+        );
+        assert!(chunks
+            .iter()
+            .all(|chunk| chunk.choices[0].delta.tool_calls.is_none()));
+        assert_eq!(
+            chunks[2].choices[0].finish_reason,
+            Some(FinishReason::FunctionCall)
+        );
+    }
+}
@@ -0,0 +1,192 @@
+use actix_web::rt::time::timeout;
+use async_openai::error::OpenAIError;
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+// Requests in flight to one provider, and how many are waiting for a slot.
+struct Slots {
+    semaphore: Arc<Semaphore>,
+    waiting: AtomicUsize,
+}
+
+fn slots(provider: &str, max_concurrency: usize) -> Arc<Slots> {
+    static SLOTS: OnceLock<Mutex<HashMap<String, Arc<Slots>>>> = OnceLock::new();
+    SLOTS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap()
+        .entry(provider.to_string())
+        .or_insert_with(|| {
+            Arc::new(Slots {
+                semaphore: Arc::new(Semaphore::new(max_concurrency)),
+                waiting: AtomicUsize::new(0),
+            })
+        })
+        .clone()
+}
+
+fn cooldowns() -> &'static Mutex<HashMap<String, Instant>> {
+    static COOLDOWNS: OnceLock<Mutex<HashMap<String, Instant>>> = OnceLock::new();
+    COOLDOWNS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// Waits for a slot with the provider, for at most `queue_timeout` and only if
+// fewer than `queue_limit` requests are already waiting. The slot is freed
+// when the permit is dropped. Providers without a limit hand out no permit.
+pub async fn acquire(
+    provider: &str,
+    max_concurrency: Option<usize>,
+    queue_limit: usize,
+    queue_timeout: Duration,
+) -> Result<Option<OwnedSemaphorePermit>, String> {
+    if let Some(until) = cooldowns().lock().unwrap().get(provider) {
+        let remaining = until.saturating_duration_since(Instant::now());
+        if !remaining.is_zero() {
+            return Err(format!(
+                "rate limited for another {}ms",
+                remaining.as_millis()
+            ));
+        }
+    }
+
+    let max_concurrency = match max_concurrency {
+        Some(max_concurrency) => max_concurrency,
+        None => return Ok(None),
+    };
+    let slots = slots(provider, max_concurrency);
+    if let Ok(permit) = slots.semaphore.clone().try_acquire_owned() {
+        return Ok(Some(permit));
+    }
+
+    if slots.waiting.fetch_add(1, Ordering::SeqCst) >= queue_limit {
+        slots.waiting.fetch_sub(1, Ordering::SeqCst);
+        return Err(format!("{} requests already waiting", queue_limit));
+    }
+    let permit = timeout(queue_timeout, slots.semaphore.clone().acquire_owned()).await;
+    slots.waiting.fetch_sub(1, Ordering::SeqCst);
+    match permit {
+        Ok(Ok(permit)) => Ok(Some(permit)),
+        _ => Err(format!("no slot within {}ms", queue_timeout.as_millis())),
+    }
+}
+
+// Stops sending requests to a provider that rate limited us until it said to
+// try again, or for `default` when it did not say. Returns whether the error
+// was a rate limit.
+pub fn rate_limited(provider: &str, error: &OpenAIError, default: Duration) -> bool {
+    let delay = match error {
+        OpenAIError::ApiError(e)
+            if e.code.as_ref().and_then(|code| code.as_str()) == Some("rate_limit_exceeded")
+                || e.r#type.as_deref() == Some("rate_limit_error") =>
+        {
+            retry_after(&e.message).unwrap_or(default)
+        }
+        OpenAIError::StreamError(message) if message.contains("429") => {
+            retry_after(message).unwrap_or(default)
+        }
+        _ => return false,
+    };
+    eprintln!(
+        "Upstream {} rate limited us, pausing it for {}ms",
+        provider,
+        delay.as_millis()
+    );
+    cooldowns()
+        .lock()
+        .unwrap()
+        .insert(provider.to_string(), Instant::now() + delay);
+    true
+}
+
+// async-openai does not expose response headers, so the `Retry-After` value
+// is read from the "Please try again in 20s" hint providers put in the
+// message instead.
+fn retry_after(message: &str) -> Option<Duration> {
+    static HINT: OnceLock<Regex> = OnceLock::new();
+    let hint = HINT.get_or_init(|| {
+        Regex::new(r"(?i)(?:try again in|retry[- ]after:?)\s*(\d+(?:\.\d+)?)\s*(ms|s)?").unwrap()
+    });
+    let caps = hint.captures(message)?;
+    let value: f64 = caps[1].parse().ok()?;
+    let millis = match caps.get(2).map(|unit| unit.as_str()) {
+        Some("ms") => value,
+        _ => value * 1000.0,
+    };
+    Some(Duration::from_millis(millis.ceil() as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::rt::{spawn, time::sleep};
+
+    #[actix_web::test]
+    async fn requests_queue_for_a_slot_up_to_the_limit() {
+        let wait = Duration::from_millis(200);
+        let held = acquire("limiter-queue", Some(1), 1, wait).await.unwrap();
+        assert!(held.is_some());
+
+        let queued = spawn(acquire("limiter-queue", Some(1), 1, wait));
+        sleep(Duration::from_millis(20)).await;
+        assert_eq!(
+            acquire("limiter-queue", Some(1), 1, wait)
+                .await
+                .unwrap_err(),
+            "1 requests already waiting"
+        );
+        drop(held);
+        assert!(queued.await.unwrap().unwrap().is_some());
+
+        let _held = acquire("limiter-timeout", Some(1), 1, wait).await.unwrap();
+        assert_eq!(
+            acquire("limiter-timeout", Some(1), 1, Duration::from_millis(10))
+                .await
+                .unwrap_err(),
+            "no slot within 10ms"
+        );
+        assert!(acquire("limiter-unlimited", None, 0, wait)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn retry_after_is_read_from_the_message() {
+        assert_eq!(
+            retry_after("Rate limit reached. Please try again in 20s."),
+            Some(Duration::from_secs(20))
+        );
+        assert_eq!(
+            retry_after("Please try again in 1.5s"),
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(
+            retry_after("Please try again in 250ms"),
+            Some(Duration::from_millis(250))
+        );
+        assert_eq!(retry_after("Retry-After: 3"), Some(Duration::from_secs(3)));
+        assert_eq!(retry_after("Too many requests"), None);
+    }
+
+    #[actix_web::test]
+    async fn rate_limited_providers_cool_down() {
+        let error = OpenAIError::StreamError(
+            "Invalid status code: 429 Too Many Requests, try again in 5s".to_string(),
+        );
+        assert!(rate_limited("limiter-429", &error, Duration::from_secs(1)));
+        let refused = acquire("limiter-429", None, 0, Duration::ZERO)
+            .await
+            .unwrap_err();
+        assert!(refused.starts_with("rate limited for another"));
+
+        let other = OpenAIError::StreamError("Invalid status code: 500".to_string());
+        assert!(!rate_limited("limiter-500", &other, Duration::from_secs(1)));
+        assert!(acquire("limiter-500", None, 0, Duration::ZERO)
+            .await
+            .is_ok());
+    }
+}
@@ -1,3 +1,24 @@
 pub mod basic;
+pub mod cache;
+pub mod canned;
+pub mod context_window;
+pub mod credentials;
+pub mod error;
+#[cfg(test)]
+pub mod fake;
+pub mod faq;
+pub mod filler;
+pub mod guardrails;
+pub mod legacy_functions;
+pub mod limiter;
+pub mod normalizer;
 pub mod openai_advanced;
 pub mod openai_sse;
+pub mod pipeline;
+pub mod routing;
+pub mod session;
+pub mod sse;
+pub mod structured;
+pub mod templating;
+pub mod tool_loop;
+pub mod upstream;
@@ -0,0 +1,737 @@
+use crate::config::env::NormalizerConfig;
+use async_openai::types::{CreateChatCompletionResponse, CreateChatCompletionStreamResponse};
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use std::sync::OnceLock;
+
+// Each assistant points Vapi at its own custom-LLM URL, so the query string is
+// where per-assistant settings live, e.g. `?normalize=true&locale=en-GB`.
+#[derive(Debug, Deserialize)]
+pub struct NormalizerOptions {
+    pub normalize: Option<bool>,
+    pub locale: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Locale {
+    // Month-first dates and 12 hour clock times.
+    American,
+    // Day-first dates, 24 hour clock times and "one hundred and five".
+    British,
+}
+
+impl Locale {
+    pub fn parse(locale: &str) -> Self {
+        match locale {
+            "en-US" | "en-CA" | "en-PH" => Locale::American,
+            _ if locale.starts_with("en-") => Locale::British,
+            _ => Locale::American,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Normalizer {
+    pub enabled: bool,
+    pub locale: Locale,
+}
+
+impl Normalizer {
+    pub fn new(config: &NormalizerConfig, options: &NormalizerOptions) -> Self {
+        Normalizer {
+            enabled: options.normalize.unwrap_or(config.enabled),
+            locale: Locale::parse(options.locale.as_deref().unwrap_or(&config.locale)),
+        }
+    }
+}
+
+const ABBREVIATIONS: [(&str, &str); 14] = [
+    ("Dr.", "Doctor"),
+    ("Mr.", "Mister"),
+    ("Mrs.", "Missus"),
+    ("Ms.", "Miz"),
+    ("St.", "Street"),
+    ("Ave.", "Avenue"),
+    ("Rd.", "Road"),
+    ("Jr.", "Junior"),
+    ("e.g.", "for example"),
+    ("i.e.", "that is"),
+    ("etc.", "et cetera"),
+    ("vs.", "versus"),
+    ("approx.", "approximately"),
+    ("min.", "minutes"),
+];
+
+// Turns streamed model text into whole sentences that read well aloud. Text is
+// only released once a sentence is complete, so each chunk Vapi receives is
+// something TTS can say in one go.
+pub struct NormalizerStream<'a> {
+    normalizer: &'a Normalizer,
+    pending: String,
+}
+
+impl<'a> NormalizerStream<'a> {
+    pub fn new(normalizer: &'a Normalizer) -> Self {
+        NormalizerStream {
+            normalizer,
+            pending: String::new(),
+        }
+    }
+
+    pub fn push(&mut self, delta: &str) -> String {
+        self.pending.push_str(delta);
+        match sentence_end(&self.pending) {
+            Some(end) => {
+                let sentences: String = self.pending.drain(..end).collect();
+                normalize(&sentences, self.normalizer.locale)
+            }
+            None => String::new(),
+        }
+    }
+
+    pub fn finish(&mut self) -> String {
+        let rest = std::mem::take(&mut self.pending);
+        normalize(&rest, self.normalizer.locale)
+    }
+}
+
+// Position just past the last complete sentence in `text`, if any.
+fn sentence_end(text: &str) -> Option<usize> {
+    let mut end = None;
+    let mut chars = text.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        let next = match chars.peek() {
+            Some((_, next)) => *next,
+            None => break,
+        };
+        let boundary = match c {
+            '\n' => true,
+            '!' | '?' => next.is_whitespace(),
+            '.' => {
+                next.is_whitespace()
+                    && !patterns().abbreviation_end.is_match(&text[..index + 1])
+                    && !list_marker_or_unfinished(text, index)
+            }
+            _ => false,
+        };
+        if boundary {
+            end = Some(index + c.len_utf8());
+        }
+    }
+    end
+}
+
+// After a digit a period only ends the sentence once a capital letter
+// follows, as in "We have 3. Then", and never in a list marker such as
+// "1. First" at the start of a line.
+fn list_marker_or_unfinished(text: &str, period: usize) -> bool {
+    let before = &text[..period];
+    let number_start = before.trim_end_matches(|c: char| c.is_ascii_digit());
+    if number_start.len() == before.len() {
+        return false;
+    }
+    let line_start = number_start.trim_end_matches([' ', '\t']);
+    line_start.is_empty()
+        || line_start.ends_with('\n')
+        || !text[period + 1..]
+            .trim_start()
+            .starts_with(char::is_uppercase)
+}
+
+pub fn normalize_chunks(
+    normalizer: &Normalizer,
+    chunks: &mut Vec<CreateChatCompletionStreamResponse>,
+) {
+    if !normalizer.enabled {
+        return;
+    }
+
+    let mut stream = NormalizerStream::new(normalizer);
+    let mut flushed = false;
+    for chunk in chunks.iter_mut() {
+        for choice in chunk.choices.iter_mut() {
+            let mut output = match &choice.delta.content {
+                Some(content) => stream.push(content),
+                None => String::new(),
+            };
+            if choice.finish_reason.is_some() {
+                output.push_str(&stream.finish());
+                flushed = true;
+            }
+            if choice.delta.content.is_some() || !output.is_empty() {
+                choice.delta.content = Some(output);
+            }
+        }
+    }
+
+    if !flushed {
+        let rest = stream.finish();
+        if let Some(choice) = chunks
+            .last_mut()
+            .and_then(|chunk| chunk.choices.first_mut())
+        {
+            choice
+                .delta
+                .content
+                .get_or_insert_with(String::new)
+                .push_str(&rest);
+        }
+    }
+
+    // Chunks that only held part of a sentence carry nothing now.
+    chunks.retain(|chunk| {
+        chunk.choices.iter().any(|choice| {
+            choice.finish_reason.is_some()
+                || choice.delta.role.is_some()
+                || choice.delta.tool_calls.is_some()
+                || choice
+                    .delta
+                    .content
+                    .as_ref()
+                    .is_none_or(|content| !content.is_empty())
+        })
+    });
+}
+
+pub fn normalize_response(normalizer: &Normalizer, response: &mut CreateChatCompletionResponse) {
+    if !normalizer.enabled {
+        return;
+    }
+
+    for choice in response.choices.iter_mut() {
+        if let Some(content) = &choice.message.content {
+            choice.message.content = Some(normalize(content, normalizer.locale));
+        }
+    }
+}
+
+struct Patterns {
+    link: Regex,
+    url: Regex,
+    heading: Regex,
+    bullet: Regex,
+    emphasis: Regex,
+    iso_date: Regex,
+    slash_date: Regex,
+    time: Regex,
+    currency: Regex,
+    percent: Regex,
+    identifier: Regex,
+    ordinal: Regex,
+    year: Regex,
+    number: Regex,
+    spaces: Regex,
+    // Each abbreviation as a whole word, so "admin." is left alone.
+    abbreviations: Vec<(Regex, &'static str)>,
+    abbreviation_end: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        link: Regex::new(r"\[([^\]]+)\]\([^)]*\)").unwrap(),
+        url: Regex::new(r"(?:https?://|www\.)([A-Za-z0-9.-]+)\S*").unwrap(),
+        heading: Regex::new(r"(?m)^\s*#{1,6}\s*").unwrap(),
+        bullet: Regex::new(r"(?m)^\s*(?:[-*+•]|\d+[.)])\s+").unwrap(),
+        emphasis: Regex::new(r"\*\*|__|`|\*|~~").unwrap(),
+        iso_date: Regex::new(r"\b(\d{4})-(\d{2})-(\d{2})\b").unwrap(),
+        slash_date: Regex::new(r"\b(\d{1,2})/(\d{1,2})/(\d{4})\b").unwrap(),
+        time: Regex::new(r"\b(\d{1,2}):(\d{2})(?:\s*([AaPp])\.?[Mm]\.?)?").unwrap(),
+        currency: Regex::new(
+            r"([$£€₹])\s?(\d{1,3}(?:,\d{3})+|\d+)(?:\.(\d{1,2}))?(?:\s?(thousand|million|billion|[kKmM])\b)?",
+        )
+        .unwrap(),
+        percent: Regex::new(r"(\d+(?:\.\d+)?)\s?%").unwrap(),
+        // A number with a leading "+", digit groups joined by dashes, or a
+        // run of seven or more digits, with any unit that follows it.
+        identifier: Regex::new(&format!(
+            r"(?:\+\d+(?:[ -]\d+)*|\b\d+(?:-\d+)+|\b\d{{7,}}(\.\d+)?)\b(\s?(?:{})\b)?",
+            UNITS.join("|")
+        ))
+        .unwrap(),
+        ordinal: Regex::new(r"\b(\d+)(?:st|nd|rd|th)\b").unwrap(),
+        year: Regex::new(r"\b(in|since|by|from|until|of|year) (1[1-9]\d\d|20\d\d)\b").unwrap(),
+        number: Regex::new(r"\b(?:\d{1,3}(?:,\d{3})+(?:\.\d+)?|\d+(?:\.\d+)?)\b").unwrap(),
+        spaces: Regex::new(r"[ \t]{2,}").unwrap(),
+        abbreviations: ABBREVIATIONS
+            .iter()
+            .map(|(abbreviation, expansion)| {
+                let pattern = format!(r"\b{}", regex::escape(abbreviation));
+                (Regex::new(&pattern).unwrap(), *expansion)
+            })
+            .collect(),
+        abbreviation_end: Regex::new(&format!(
+            r"\b(?:{})$",
+            ABBREVIATIONS
+                .iter()
+                .map(|(abbreviation, _)| regex::escape(abbreviation))
+                .collect::<Vec<_>>()
+                .join("|")
+        ))
+        .unwrap(),
+    })
+}
+
+pub fn normalize(text: &str, locale: Locale) -> String {
+    if text.trim().is_empty() {
+        return text.to_string();
+    }
+
+    let p = patterns();
+    let text = p.link.replace_all(text, "$1");
+    let text = p.url.replace_all(&text, |caps: &Captures| {
+        caps[1]
+            .trim_start_matches("www.")
+            .trim_end_matches('.')
+            .replace('.', " dot ")
+    });
+    let text = p.heading.replace_all(&text, "");
+    let text = p.bullet.replace_all(&text, "");
+    let text = p.emphasis.replace_all(&text, "");
+
+    let text = p.iso_date.replace_all(&text, |caps: &Captures| {
+        spoken_date(&caps[1], &caps[2], &caps[3], locale).unwrap_or_else(|| caps[0].to_string())
+    });
+    let text = p.slash_date.replace_all(&text, |caps: &Captures| {
+        let (month, day) = match locale {
+            Locale::American => (&caps[1], &caps[2]),
+            Locale::British => (&caps[2], &caps[1]),
+        };
+        spoken_date(&caps[3], month, day, locale).unwrap_or_else(|| caps[0].to_string())
+    });
+    let text = p.time.replace_all(&text, |caps: &Captures| {
+        spoken_time(caps, locale).unwrap_or_else(|| caps[0].to_string())
+    });
+    let text = p
+        .currency
+        .replace_all(&text, |caps: &Captures| spoken_currency(caps, locale));
+    let text = p.percent.replace_all(&text, |caps: &Captures| {
+        format!("{} percent", spoken_number(&caps[1], locale))
+    });
+    let text = p
+        .identifier
+        .replace_all(&text, |caps: &Captures| spoken_identifier(caps, locale));
+    let text = p
+        .ordinal
+        .replace_all(&text, |caps: &Captures| match caps[1].parse() {
+            Ok(n) => ordinal_words(n, locale),
+            Err(_) => caps[0].to_string(),
+        });
+    let text = p.year.replace_all(&text, |caps: &Captures| {
+        format!("{} {}", &caps[1], year_words(caps[2].parse().unwrap_or(0)))
+    });
+    let text = p
+        .number
+        .replace_all(&text, |caps: &Captures| spoken_number(&caps[0], locale));
+
+    let mut text = text.replace(" & ", " and ");
+    for (abbreviation, expansion) in &p.abbreviations {
+        text = abbreviation.replace_all(&text, *expansion).to_string();
+    }
+    p.spaces.replace_all(&text, " ").to_string()
+}
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+fn spoken_date(year: &str, month: &str, day: &str, locale: Locale) -> Option<String> {
+    let year: u64 = year.parse().ok()?;
+    let month: usize = month.parse().ok()?;
+    let day: u64 = day.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let month = MONTHS[month - 1];
+    let day = ordinal_words(day, locale);
+    Some(match locale {
+        Locale::American => format!("{} {}, {}", month, day, year_words(year)),
+        Locale::British => format!("the {} of {}, {}", day, month, year_words(year)),
+    })
+}
+
+fn spoken_time(caps: &Captures, locale: Locale) -> Option<String> {
+    let hour: u64 = caps[1].parse().ok()?;
+    let minute: u64 = caps[2].parse().ok()?;
+    if hour > 23 || minute > 59 {
+        return None;
+    }
+
+    let minutes = |on_the_hour: &str| match minute {
+        0 => on_the_hour.to_string(),
+        1..=9 => format!(" oh {}", number_words(minute, locale)),
+        _ => format!(" {}", number_words(minute, locale)),
+    };
+
+    if let Some(meridiem) = caps.get(3) {
+        let suffix = if meridiem.as_str().eq_ignore_ascii_case("p") {
+            "PM"
+        } else {
+            "AM"
+        };
+        return Some(format!(
+            "{}{} {}",
+            number_words(hour, locale),
+            minutes(""),
+            suffix
+        ));
+    }
+
+    Some(match locale {
+        Locale::British if hour >= 13 || hour == 0 => {
+            format!("{}{}", number_words(hour, locale), minutes(" hundred"))
+        }
+        Locale::American if hour >= 12 || hour == 0 => {
+            let twelve_hour = if hour.is_multiple_of(12) {
+                12
+            } else {
+                hour % 12
+            };
+            let suffix = if hour >= 12 { "PM" } else { "AM" };
+            format!(
+                "{}{} {}",
+                number_words(twelve_hour, locale),
+                minutes(""),
+                suffix
+            )
+        }
+        _ => format!("{}{}", number_words(hour, locale), minutes(" o'clock")),
+    })
+}
+
+fn spoken_currency(caps: &Captures, locale: Locale) -> String {
+    let (major, minor) = match &caps[1] {
+        "£" => ("pound", "pence"),
+        "€" => ("euro", "cents"),
+        "₹" => ("rupee", "paise"),
+        _ => ("dollar", "cents"),
+    };
+    let whole: u64 = caps[2].replace(',', "").parse().unwrap_or(0);
+    let fraction = caps.get(3).map(|m| m.as_str());
+
+    // "$1.5 million" is one point five million dollars, not cents.
+    if let Some(scale) = caps.get(4) {
+        let scale = match scale.as_str() {
+            "k" | "K" => "thousand",
+            "m" | "M" => "million",
+            other => other,
+        };
+        let amount = match fraction {
+            Some(fraction) => spoken_number(&format!("{}.{}", whole, fraction), locale),
+            None => number_words(whole, locale),
+        };
+        return format!("{} {} {}s", amount, scale, major);
+    }
+
+    let mut spoken = format!(
+        "{} {}{}",
+        number_words(whole, locale),
+        major,
+        if whole == 1 { "" } else { "s" }
+    );
+    let cents: u64 = match fraction {
+        Some(fraction) if fraction.len() == 1 => fraction.parse::<u64>().unwrap_or(0) * 10,
+        Some(fraction) => fraction.parse().unwrap_or(0),
+        None => 0,
+    };
+    if cents > 0 {
+        spoken.push_str(&format!(" and {} {}", number_words(cents, locale), minor));
+    }
+    spoken
+}
+
+fn spoken_number(number: &str, locale: Locale) -> String {
+    let number = number.replace(',', "");
+    let (whole, fraction) = match number.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (number.as_str(), None),
+    };
+    // Too long to say as an amount, even with thousands separators.
+    if whole.len() > LONGEST_AMOUNT {
+        return digit_words(&number);
+    }
+    let mut spoken = match whole.parse() {
+        Ok(whole) => number_words(whole, locale),
+        Err(_) => return number.to_string(),
+    };
+    if let Some(fraction) = fraction {
+        spoken.push_str(" point ");
+        spoken.push_str(&digit_words(fraction));
+    }
+    spoken
+}
+
+// Words after a number that make it a quantity rather than an identifier.
+const UNITS: [&str; 16] = [
+    "people",
+    "users",
+    "customers",
+    "times",
+    "points",
+    "steps",
+    "views",
+    "units",
+    "miles",
+    "meters",
+    "kilometers",
+    "km",
+    "kg",
+    "pounds",
+    "dollars",
+    "euros",
+];
+
+// Phone, order and account numbers are read digit by digit, a group at a
+// time: "+1 415-555-0123" is "plus one, four one five, five five five, zero
+// one two three". A run of digits followed by a unit, or with a decimal part,
+// is an amount after all. Short dashed pairs such as "3-5" are ranges.
+fn spoken_identifier(caps: &Captures, locale: Locale) -> String {
+    let unit = caps.get(2).map_or("", |unit| unit.as_str());
+    let number = &caps[0][..caps[0].len() - unit.len()];
+    let plus = number.starts_with('+');
+    let groups: Vec<&str> = number.trim_start_matches('+').split([' ', '-']).collect();
+    let digits = number.chars().filter(char::is_ascii_digit).count();
+    let amount =
+        !plus && (caps.get(1).is_some() || !unit.is_empty() || (groups.len() == 2 && digits < 7));
+    let spoken = if amount {
+        groups
+            .iter()
+            .map(|group| spoken_number(group, locale))
+            .collect::<Vec<_>>()
+            .join("-")
+    } else {
+        groups
+            .iter()
+            .map(|group| digit_words(group))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    format!("{}{}{}", if plus { "plus " } else { "" }, spoken, unit)
+}
+
+// Digits read out one at a time: "4111" is "four one one one".
+fn digit_words(digits: &str) -> String {
+    digits
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .map(|digit| ONES[digit as usize])
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+const ONES: [&str; 20] = [
+    "zero",
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "ten",
+    "eleven",
+    "twelve",
+    "thirteen",
+    "fourteen",
+    "fifteen",
+    "sixteen",
+    "seventeen",
+    "eighteen",
+    "nineteen",
+];
+
+const TENS: [&str; 10] = [
+    "", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+];
+
+const SCALES: [(u64, &str); 4] = [
+    (1_000_000_000_000, "trillion"),
+    (1_000_000_000, "billion"),
+    (1_000_000, "million"),
+    (1_000, "thousand"),
+];
+
+// Digits in the largest number read as an amount, just under a quadrillion.
+const LONGEST_AMOUNT: usize = 15;
+
+pub fn number_words(n: u64, locale: Locale) -> String {
+    // Past the largest scale `below_thousand` would be handed more than
+    // three digits.
+    if n >= SCALES[0].0 * 1000 {
+        return digit_words(&n.to_string());
+    }
+    if n < 1000 {
+        return below_thousand(n, locale);
+    }
+
+    let mut parts = Vec::new();
+    let mut rest = n;
+    for (scale, name) in SCALES {
+        if rest >= scale {
+            parts.push(format!("{} {}", below_thousand(rest / scale, locale), name));
+            rest %= scale;
+        }
+    }
+    if rest > 0 {
+        if rest < 100 && locale == Locale::British {
+            parts.push(format!("and {}", below_hundred(rest)));
+        } else {
+            parts.push(below_thousand(rest, locale));
+        }
+    }
+    parts.join(" ")
+}
+
+fn below_thousand(n: u64, locale: Locale) -> String {
+    if n < 100 {
+        return below_hundred(n);
+    }
+    let hundreds = format!("{} hundred", ONES[(n / 100) as usize]);
+    match (n % 100, locale) {
+        (0, _) => hundreds,
+        (rest, Locale::British) => format!("{} and {}", hundreds, below_hundred(rest)),
+        (rest, Locale::American) => format!("{} {}", hundreds, below_hundred(rest)),
+    }
+}
+
+fn below_hundred(n: u64) -> String {
+    match n {
+        0..=19 => ONES[n as usize].to_string(),
+        _ if n.is_multiple_of(10) => TENS[(n / 10) as usize].to_string(),
+        _ => format!("{}-{}", TENS[(n / 10) as usize], ONES[(n % 10) as usize]),
+    }
+}
+
+fn ordinal_words(n: u64, locale: Locale) -> String {
+    let cardinal = number_words(n, locale);
+    let split = cardinal.rfind([' ', '-']).map_or(0, |index| index + 1);
+    let (head, last) = cardinal.split_at(split);
+    let last = match last {
+        "one" => "first".to_string(),
+        "two" => "second".to_string(),
+        "three" => "third".to_string(),
+        "five" => "fifth".to_string(),
+        "eight" => "eighth".to_string(),
+        "nine" => "ninth".to_string(),
+        "twelve" => "twelfth".to_string(),
+        _ if last.ends_with('y') => format!("{}ieth", &last[..last.len() - 1]),
+        _ => format!("{}th", last),
+    };
+    format!("{}{}", head, last)
+}
+
+fn year_words(year: u64) -> String {
+    let (century, rest) = (year / 100, year % 100);
+    match (century, rest) {
+        (_, 0) if year.is_multiple_of(1000) => number_words(year, Locale::American),
+        (20, 1..=9) => number_words(year, Locale::American),
+        (_, 0) => format!("{} hundred", below_hundred(century)),
+        (_, 1..=9) => format!("{} oh {}", below_hundred(century), ONES[rest as usize]),
+        _ => format!("{} {}", below_hundred(century), below_hundred(rest)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_numbers_are_read_digit_by_digit() {
+        assert_eq!(
+            normalize("Card 4111111111111111 is on file.", Locale::American),
+            "Card four one one one one one one one one one one one one one one one is on file."
+        );
+        for digits in [
+            "1000000000000000",
+            "12345678901234567890",
+            "99999999999999999999",
+        ] {
+            let spoken = normalize(digits, Locale::American);
+            assert_eq!(spoken.split(' ').count(), digits.len(), "{}", spoken);
+        }
+        assert_eq!(
+            number_words(u64::MAX, Locale::British).split(' ').count(),
+            20
+        );
+        assert_eq!(
+            normalize("999,999,999,999,999", Locale::American),
+            "nine hundred ninety-nine trillion nine hundred ninety-nine billion nine hundred ninety-nine million nine hundred ninety-nine thousand nine hundred ninety-nine"
+        );
+    }
+
+    #[test]
+    fn abbreviations_only_expand_as_whole_words() {
+        assert_eq!(
+            normalize(
+                "Ask the admin. Devs. vs. ops, e.g. Dr. Lee.",
+                Locale::American
+            ),
+            "Ask the admin. Devs. versus ops, for example Doctor Lee."
+        );
+        assert_eq!(sentence_end("Ask the admin. Then"), Some(14));
+        assert_eq!(sentence_end("Call Dr. Lee"), None);
+    }
+
+    #[test]
+    fn phone_and_order_numbers_are_read_digit_by_digit() {
+        assert_eq!(
+            normalize("Call +14155550123 now.", Locale::American),
+            "Call plus one four one five five five five zero one two three now."
+        );
+        assert_eq!(
+            normalize("Call +1 415-555-0123.", Locale::American),
+            "Call plus one, four one five, five five five, zero one two three."
+        );
+        assert_eq!(
+            normalize("Dial 555-123-4567.", Locale::American),
+            "Dial five five five, one two three, four five six seven."
+        );
+        assert_eq!(
+            normalize("Your order 12345678 shipped.", Locale::American),
+            "Your order one two three four five six seven eight shipped."
+        );
+    }
+
+    #[test]
+    fn amounts_and_ranges_are_still_read_as_numbers() {
+        assert_eq!(
+            normalize("It takes 3-5 days.", Locale::American),
+            "It takes three-five days."
+        );
+        assert_eq!(
+            normalize("We have 1234567 users and $2500000.", Locale::American),
+            "We have one million two hundred thirty-four thousand five hundred sixty-seven users and two million five hundred thousand dollars."
+        );
+        assert_eq!(
+            normalize("Pay 1234567.50 today.", Locale::American),
+            "Pay one million two hundred thirty-four thousand five hundred sixty-seven point five zero today."
+        );
+        assert_eq!(
+            normalize("We have 42 rooms.", Locale::American),
+            "We have forty-two rooms."
+        );
+    }
+
+    #[test]
+    fn a_period_after_a_number_ends_the_sentence_before_a_capital() {
+        assert_eq!(sentence_end("We have 3. Then"), Some(10));
+        // Nothing yet to tell a sentence from a decimal or a list.
+        assert_eq!(sentence_end("We have 3. "), None);
+        assert_eq!(sentence_end("We have 3. and"), None);
+        assert_eq!(sentence_end("1. First"), None);
+        assert_eq!(sentence_end("Steps:\n  2. Second"), Some(7));
+    }
+}
@@ -0,0 +1,187 @@
+use crate::api::custom_llm::{
+    cache::{self, CacheKey},
+    canned, context_window, credentials,
+    error::CustomLlmError,
+    faq, filler,
+    guardrails::{self, Guardrails},
+    legacy_functions,
+    normalizer::{self, Normalizer, NormalizerOptions},
+    routing, sse, structured, templating, tool_loop,
+    upstream::Upstream,
+};
+use crate::config::env;
+use crate::types::vapi::CustomLlmRequest;
+use actix_web::{HttpRequest, HttpResponse};
+use async_openai::types::CreateChatCompletionRequest;
+
+// Answers one custom-LLM turn: the request is routed and billed, answered
+// from the FAQ or the cache when it can be, fitted into the context window,
+// and otherwise sent upstream, with tools run and the answer filtered and
+// normalized on the way back. `rewrite` is the endpoint's own change to the
+// prompt; it is applied after the FAQ and cache lookups, which see the prompt
+// as the caller sent it.
+pub async fn complete(
+    req: &HttpRequest,
+    body: CustomLlmRequest,
+    options: &NormalizerOptions,
+    rewrite: fn(&mut CreateChatCompletionRequest),
+) -> Result<HttpResponse, CustomLlmError> {
+    let env_config = env::load_env_config();
+    let mut upstream = Upstream::from_config(&env_config.llm);
+    let guardrails = Guardrails::from_config(&env_config.guardrails);
+    let normalizer = Normalizer::new(&env_config.normalizer, options);
+
+    let CustomLlmRequest {
+        completion: mut request,
+        context,
+    } = body;
+    let call_id = context.call_id().unwrap_or("unknown").to_string();
+
+    let legacy = legacy_functions::to_tools(&mut request);
+    if let Some(target) = routing::route(&mut request, &context, &env_config.routing) {
+        upstream.prefer(target);
+    }
+    let api_key = credentials::api_key(&env_config.credentials, &env_config.auth, req, &context);
+    if let Some(api_key) = &api_key {
+        upstream.use_api_key("openai", api_key);
+    }
+    templating::render_system_prompts(&mut request, &context);
+
+    if let Some(answer) = faq::answer(&env_config.faq, &request) {
+        println!("Turn for call {} answered from the FAQ", call_id);
+        return Ok(canned::respond(
+            &request,
+            &answer,
+            ("X-Answer-Source", "faq"),
+        ));
+    }
+
+    let cache_key = CacheKey::new(
+        &env_config,
+        &request,
+        &context,
+        api_key.as_deref(),
+        &normalizer,
+    );
+    if let Some(text) = cache_key
+        .as_ref()
+        .and_then(|key| key.get(&env_config.cache))
+    {
+        println!("Turn for call {} answered from cache", call_id);
+        return Ok(canned::respond(
+            &request,
+            &text,
+            ("X-Answer-Source", "cache"),
+        ));
+    }
+
+    rewrite(&mut request);
+    context_window::fit(&upstream, &mut request, &env_config.context).await;
+
+    if let Some(config) = context
+        .assistant_id()
+        .and_then(|id| env_config.structured_output.get(id))
+    {
+        return structured::respond(
+            &upstream,
+            request,
+            config,
+            &env_config,
+            &guardrails,
+            &normalizer,
+            &call_id,
+            legacy,
+        )
+        .await;
+    }
+
+    // Check if the stream is false in the request
+    if request.stream.unwrap_or(true) {
+        let filler = filler::choose(&env_config.filler, &request);
+        let model = request.model.clone();
+        let apology = env_config.llm.apology_message.clone();
+        let turn = {
+            let (call_id, model) = (call_id.clone(), model.clone());
+            async move {
+                let mut response_body = String::new();
+                let mut turn =
+                    match tool_loop::stream_with_tools(&upstream, request, &env_config.tools).await
+                    {
+                        // Every provider is saturated, so ask the caller to hold on
+                        // rather than fail the turn.
+                        Err(CustomLlmError::Busy(reason)) => {
+                            println!("Providers busy for call {}: {}", call_id, reason);
+                            let chunk =
+                                sse::text_chunk(&call_id, &model, &env_config.llm.busy_message);
+                            return Ok(("busy".to_string(), sse::data(&chunk)));
+                        }
+                        result => result?,
+                    };
+                println!("Turn for call {} answered by {}", call_id, turn.provider);
+                if legacy {
+                    legacy_functions::to_function_chunks(&mut turn.chunks);
+                }
+                guardrails::filter_chunks(&guardrails, &mut turn.chunks);
+                normalizer::normalize_chunks(&normalizer, &mut turn.chunks);
+
+                if let (None, Some(key), Some(text)) =
+                    (&turn.error, &cache_key, cache::chunks_text(&turn.chunks))
+                {
+                    key.put(&env_config.cache, text);
+                }
+
+                for ccr in &turn.chunks {
+                    response_body.push_str(&sse::data(ccr));
+                }
+
+                // Tell Vapi what went wrong, then give the caller something to hear
+                // instead of dead air.
+                if let Some(e) = turn.error {
+                    eprintln!(
+                        "Stream for call {} from {} failed: {}",
+                        call_id, turn.provider, e
+                    );
+                    let id = turn
+                        .chunks
+                        .last()
+                        .map(|ccr| ccr.id.clone())
+                        .unwrap_or_default();
+                    response_body.push_str(&sse::error_event(&CustomLlmError::from(e)));
+                    response_body.push_str(&sse::data(&sse::text_chunk(
+                        &id,
+                        &model,
+                        &env_config.llm.apology_message,
+                    )));
+                }
+
+                Ok((turn.provider, response_body))
+            }
+        };
+        filler::respond(turn, filler, &call_id, &model, &apology).await
+    } else {
+        // If stream is false, call the normal chat create
+        let model = request.model.clone();
+        let (provider, mut response) =
+            match tool_loop::create_with_tools(&upstream, request, &env_config.tools).await {
+                Err(CustomLlmError::Busy(reason)) => {
+                    println!("Providers busy for call {}: {}", call_id, reason);
+                    return Ok(HttpResponse::Ok()
+                        .append_header(("X-Answer-Source", "busy"))
+                        .json(canned::response(&env_config.llm.busy_message, &model)));
+                }
+                result => result?,
+            };
+        println!("Turn for call {} answered by {}", call_id, provider);
+        if legacy {
+            legacy_functions::to_function_response(&mut response);
+        }
+        guardrails::filter_response(&guardrails, &mut response);
+        normalizer::normalize_response(&normalizer, &mut response);
+        if let (Some(key), Some(text)) = (&cache_key, cache::response_text(&response)) {
+            key.put(&env_config.cache, text);
+        }
+        Ok(HttpResponse::Ok()
+            .append_header(("X-LLM-Provider", provider))
+            .json(response))
+    }
+}
@@ -0,0 +1,162 @@
+use crate::api::custom_llm::upstream::Target;
+use crate::config::env::{ModelRoute, RoutingConfig};
+use crate::types::vapi::CallContext;
+use async_openai::types::CreateChatCompletionRequest;
+use rand::Rng;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+// Resolves a logical model name such as `fast` or `support-v2` to one of the
+// routes configured for it, and rewrites the request for that route. Models
+// that are not aliases pass through unchanged. The returned target keeps the
+// route's model, so it goes to `Upstream::prefer` along with the fallbacks
+// that can serve it.
+pub fn route(
+    request: &mut CreateChatCompletionRequest,
+    context: &CallContext,
+    config: &RoutingConfig,
+) -> Option<Target> {
+    let routes = config.aliases.get(&request.model)?;
+    let route = pick(routes, context.call_id())?;
+
+    println!(
+        "Routing model {} for call {} to {}/{}",
+        request.model,
+        context.call_id().unwrap_or("unknown"),
+        route.target.provider,
+        route.target.model.as_deref().unwrap_or_default()
+    );
+
+    if let Some(model) = &route.target.model {
+        request.model = model.clone();
+    }
+    if route.temperature.is_some() {
+        request.temperature = route.temperature;
+    }
+    if route.max_tokens.is_some() {
+        request.max_tokens = route.max_tokens;
+    }
+    Some(Target::from_config(&route.target))
+}
+
+// Picks a route in proportion to its weight. The pick is derived from the
+// call id so every turn of a call lands on the same route; requests without
+// one are split at random.
+fn pick<'a>(routes: &'a [ModelRoute], call_id: Option<&str>) -> Option<&'a ModelRoute> {
+    let total: u64 = routes.iter().map(|route| route.weight as u64).sum();
+    if total == 0 {
+        return routes.first();
+    }
+
+    let mut point = match call_id {
+        Some(call_id) => {
+            let mut hasher = DefaultHasher::new();
+            call_id.hash(&mut hasher);
+            hasher.finish() % total
+        }
+        None => rand::thread_rng().gen_range(0..total),
+    };
+    for route in routes {
+        if point < route.weight as u64 {
+            return Some(route);
+        }
+        point -= route.weight as u64;
+    }
+    routes.last()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::custom_llm::upstream::Upstream;
+    use crate::config::env::{LlmConfig, LlmTarget};
+    use crate::types::vapi::VapiCall;
+    use std::collections::HashMap;
+
+    fn target(provider: &str, model: Option<&str>) -> LlmTarget {
+        LlmTarget {
+            provider: provider.to_string(),
+            base_url: "http://127.0.0.1:1".to_string(),
+            api_key: String::new(),
+            model: model.map(str::to_string),
+            max_concurrency: None,
+        }
+    }
+
+    fn route(provider: &str, model: &str, weight: u32) -> ModelRoute {
+        ModelRoute {
+            target: target(provider, Some(model)),
+            temperature: None,
+            max_tokens: None,
+            weight,
+        }
+    }
+
+    #[test]
+    fn routed_calls_keep_their_route_and_fallbacks_keep_their_model() {
+        let routes = vec![
+            route("openai", "gpt-4o-mini", 0),
+            route("groq", "llama-3.1-8b", 1),
+        ];
+        for call_id in ["call-1", "call-2", "call-3"] {
+            assert_eq!(
+                pick(&routes, Some(call_id)).unwrap().target.provider,
+                "groq"
+            );
+        }
+        let split = vec![
+            route("openai", "gpt-4o-mini", 50),
+            route("groq", "llama-3.1-8b", 50),
+        ];
+        let first = pick(&split, Some("call-1"))
+            .unwrap()
+            .target
+            .provider
+            .clone();
+        for _ in 0..10 {
+            assert_eq!(pick(&split, Some("call-1")).unwrap().target.provider, first);
+        }
+
+        let mut request: CreateChatCompletionRequest =
+            serde_json::from_value(serde_json::json!({"model": "fast", "messages": []})).unwrap();
+        let context = CallContext {
+            call: Some(VapiCall {
+                id: Some("call-1".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let config = RoutingConfig {
+            aliases: HashMap::from([("fast".to_string(), routes)]),
+        };
+        let mut upstream = Upstream::from_config(&LlmConfig {
+            fallback_chain: vec![
+                target("openai", None),
+                target("groq", None),
+                target("openai", Some("gpt-4o")),
+            ],
+            first_token_timeout_ms: 0,
+            hedge_delay_ms: None,
+            apology_message: String::new(),
+            queue_limit: 0,
+            queue_timeout_ms: 0,
+            rate_limit_cooldown_ms: 0,
+            busy_message: String::new(),
+        });
+        upstream.prefer(super::route(&mut request, &context, &config).unwrap());
+
+        let chain: Vec<(&str, Option<&str>)> = upstream
+            .targets
+            .iter()
+            .map(|target| (target.provider.as_str(), target.model.as_deref()))
+            .collect();
+        assert_eq!(
+            chain,
+            vec![
+                ("groq", Some("llama-3.1-8b")),
+                ("groq", Some("llama-3.1-8b")),
+                ("openai", Some("gpt-4o")),
+            ]
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use crate::config::env;
+use actix_web::{web::Path, HttpResponse};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
+
+// Data gathered during a call that is not spoken, such as the fields of a
+// structured response, kept in memory per call id.
+#[derive(Default)]
+struct Session {
+    fields: Map<String, Value>,
+    ended_at: Option<Instant>,
+}
+
+fn sessions() -> MutexGuard<'static, HashMap<String, Session>> {
+    static SESSIONS: OnceLock<Mutex<HashMap<String, Session>>> = OnceLock::new();
+    let retention = Duration::from_secs(env::load_env_config().calls.session_retention_secs);
+    let mut sessions = SESSIONS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap();
+    evict(&mut sessions, retention);
+    sessions
+}
+
+// Sessions of calls that ended more than `SESSION_RETENTION_SECS` ago are
+// dropped whenever the sessions are looked at.
+fn evict(sessions: &mut HashMap<String, Session>, retention: Duration) {
+    sessions.retain(|_, session| {
+        session
+            .ended_at
+            .is_none_or(|ended_at| ended_at.elapsed() < retention)
+    });
+}
+
+// Later values for a field replace earlier ones.
+pub fn record(call_id: &str, fields: Map<String, Value>) {
+    sessions()
+        .entry(call_id.to_string())
+        .or_default()
+        .fields
+        .extend(fields);
+}
+
+// The session stays readable for a while after the call, for whoever
+// collects its fields once it is over.
+pub fn ended(call_id: &str) {
+    if let Some(session) = sessions().get_mut(call_id) {
+        session.ended_at.get_or_insert_with(Instant::now);
+    }
+}
+
+pub async fn session(call_id: Path<String>) -> HttpResponse {
+    match sessions().get(call_id.as_str()) {
+        Some(session) => HttpResponse::Ok().json(&session.fields),
+        None => HttpResponse::NotFound().json(json!({ "error": "no session for this call" })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::calls::lifecycle::{self, CallState};
+    use crate::types::vapi::VapiCall;
+    use actix_web::{http::StatusCode, web, App};
+
+    #[actix_web::test]
+    async fn sessions_outlive_their_call_only_for_the_retention() {
+        let fields = |value: Value| value.as_object().unwrap().clone();
+        record("session-ended", fields(json!({"intent": "booking"})));
+        record("session-ended", fields(json!({"date": "2026-11-02"})));
+        lifecycle::transition(
+            &VapiCall {
+                id: Some("session-ended".to_string()),
+                ..Default::default()
+            },
+            CallState::Ended,
+            "status-update",
+        );
+        assert!(sessions()["session-ended"].ended_at.is_some());
+
+        let app = actix_web::test::init_service(
+            App::new().route("/sessions/{call_id}", web::get().to(session)),
+        )
+        .await;
+        let request = actix_web::test::TestRequest::get()
+            .uri("/sessions/session-ended")
+            .to_request();
+        let body: Value = actix_web::test::call_and_read_body_json(&app, request).await;
+        assert_eq!(body, json!({"intent": "booking", "date": "2026-11-02"}));
+
+        let mut kept = HashMap::from([
+            ("live".to_string(), Session::default()),
+            (
+                "ended".to_string(),
+                Session {
+                    ended_at: Some(Instant::now() - Duration::from_secs(5)),
+                    ..Default::default()
+                },
+            ),
+        ]);
+        evict(&mut kept, Duration::from_secs(60));
+        assert_eq!(kept.len(), 2);
+        evict(&mut kept, Duration::from_secs(1));
+        assert!(kept.contains_key("live"));
+        assert!(!kept.contains_key("ended"));
+
+        let request = actix_web::test::TestRequest::get()
+            .uri("/sessions/never-recorded")
+            .to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,94 @@
+use crate::api::custom_llm::error::CustomLlmError;
+use async_openai::types::{
+    ChatChoiceStream, ChatCompletionMessageToolCallChunk, ChatCompletionStreamResponseDelta,
+    CreateChatCompletionResponse, CreateChatCompletionStreamResponse, FinishReason,
+    FunctionCallStream, Role,
+};
+
+pub fn data(chunk: &CreateChatCompletionStreamResponse) -> String {
+    format!("data: {}\n\n", serde_json::to_string(chunk).unwrap())
+}
+
+pub fn error_event(error: &CustomLlmError) -> String {
+    format!("event: error\ndata: {}\n\n", error.body())
+}
+
+// A complete assistant turn in a single chunk, for text we produce ourselves
+// rather than stream from a provider.
+pub fn text_chunk(id: &str, model: &str, text: &str) -> CreateChatCompletionStreamResponse {
+    let mut chunk = content_chunk(id, model, text);
+    chunk.choices[0].finish_reason = Some(FinishReason::Stop);
+    chunk
+}
+
+// Text that more of the same turn will follow.
+#[allow(deprecated)]
+pub fn content_chunk(id: &str, model: &str, text: &str) -> CreateChatCompletionStreamResponse {
+    CreateChatCompletionStreamResponse {
+        id: id.to_string(),
+        choices: vec![ChatChoiceStream {
+            index: 0,
+            delta: ChatCompletionStreamResponseDelta {
+                content: Some(text.to_string()),
+                function_call: None,
+                tool_calls: None,
+                role: Some(Role::Assistant),
+            },
+            finish_reason: None,
+            logprobs: None,
+        }],
+        created: chrono::Utc::now().timestamp() as u32,
+        model: model.to_string(),
+        system_fingerprint: None,
+        object: "chat.completion.chunk".to_string(),
+    }
+}
+
+// A complete response as a single chunk, for answers that had to be finished
+// before they could be checked but were asked for as a stream.
+#[allow(deprecated)]
+pub fn response_chunk(
+    response: &CreateChatCompletionResponse,
+) -> CreateChatCompletionStreamResponse {
+    CreateChatCompletionStreamResponse {
+        id: response.id.clone(),
+        choices: response
+            .choices
+            .iter()
+            .map(|choice| ChatChoiceStream {
+                index: choice.index,
+                delta: ChatCompletionStreamResponseDelta {
+                    content: choice.message.content.clone(),
+                    function_call: choice.message.function_call.as_ref().map(|call| {
+                        FunctionCallStream {
+                            name: Some(call.name.clone()),
+                            arguments: Some(call.arguments.clone()),
+                        }
+                    }),
+                    tool_calls: choice.message.tool_calls.as_ref().map(|calls| {
+                        calls
+                            .iter()
+                            .enumerate()
+                            .map(|(index, call)| ChatCompletionMessageToolCallChunk {
+                                index: index as i32,
+                                id: Some(call.id.clone()),
+                                r#type: Some(call.r#type.clone()),
+                                function: Some(FunctionCallStream {
+                                    name: Some(call.function.name.clone()),
+                                    arguments: Some(call.function.arguments.clone()),
+                                }),
+                            })
+                            .collect()
+                    }),
+                    role: Some(choice.message.role),
+                },
+                finish_reason: choice.finish_reason,
+                logprobs: None,
+            })
+            .collect(),
+        created: response.created,
+        model: response.model.clone(),
+        system_fingerprint: response.system_fingerprint.clone(),
+        object: "chat.completion.chunk".to_string(),
+    }
+}
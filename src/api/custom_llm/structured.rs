@@ -0,0 +1,251 @@
+use crate::api::custom_llm::{
+    canned,
+    error::CustomLlmError,
+    guardrails::{self, Guardrails},
+    legacy_functions,
+    normalizer::{self, Normalizer},
+    session, sse, tool_loop,
+    upstream::Upstream,
+};
+use crate::config::env::{EnvConfig, StructuredOutputConfig};
+use actix_web::HttpResponse;
+use async_openai::types::{
+    ChatCompletionRequestAssistantMessage, ChatCompletionRequestMessage,
+    ChatCompletionRequestSystemMessage, ChatCompletionResponseFormat,
+    ChatCompletionResponseFormatType, CreateChatCompletionRequest, Role,
+};
+use serde_json::{Map, Value};
+
+// Answers a turn for an assistant that must reply with JSON matching a
+// schema. Invalid replies are sent back to the model with what was wrong, up
+// to the configured number of times. Only the speech field is spoken; the
+// other fields are recorded on the call session. Replies are not written to
+// the response cache, since a cached reply would carry only the speech and
+// the other fields would never reach the session. Tool calls are converted
+// back to a function call when the request used legacy `functions`.
+#[allow(clippy::too_many_arguments)]
+pub async fn respond(
+    upstream: &Upstream,
+    mut request: CreateChatCompletionRequest,
+    config: &StructuredOutputConfig,
+    env_config: &EnvConfig,
+    guardrails: &Guardrails,
+    normalizer: &Normalizer,
+    call_id: &str,
+    legacy: bool,
+) -> Result<HttpResponse, CustomLlmError> {
+    let stream = request.stream.unwrap_or(true);
+    // The whole reply has to be validated before any of it is spoken.
+    request.stream = Some(false);
+    request.response_format = Some(ChatCompletionResponseFormat {
+        r#type: ChatCompletionResponseFormatType::JsonObject,
+    });
+    request.messages.push(system_message(format!(
+        "Respond with only a JSON object that matches this JSON schema: {}. Put what should be said to the caller in the \"{}\" field.",
+        config.schema, config.speech_field
+    )));
+
+    let mut attempt = 0;
+    loop {
+        let result =
+            tool_loop::create_with_tools(upstream, request.clone(), &env_config.tools).await;
+        request.stream = Some(stream);
+        let (provider, mut response) = match result {
+            Err(CustomLlmError::Busy(reason)) => {
+                println!("Providers busy for call {}: {}", call_id, reason);
+                return Ok(canned::respond(
+                    &request,
+                    &env_config.llm.busy_message,
+                    ("X-Answer-Source", "busy"),
+                ));
+            }
+            result => result?,
+        };
+
+        let message = match response.choices.first() {
+            Some(choice) if choice.message.tool_calls.is_none() => &choice.message,
+            // Tool calls are for Vapi to run.
+            _ => {
+                if legacy {
+                    legacy_functions::to_function_response(&mut response);
+                }
+                let mut reply = HttpResponse::Ok();
+                reply.append_header(("X-LLM-Provider", provider));
+                return Ok(if stream {
+                    reply
+                        .append_header(("Content-Type", "text/event-stream"))
+                        .body(sse::data(&sse::response_chunk(&response)))
+                } else {
+                    reply.json(response)
+                });
+            }
+        };
+        let content = message.content.clone().unwrap_or_default();
+
+        let errors = match parse(&content, config) {
+            Ok((speech, fields)) => {
+                session::record(call_id, fields);
+                response.choices[0].message.content = Some(speech);
+                guardrails::filter_response(guardrails, &mut response);
+                normalizer::normalize_response(normalizer, &mut response);
+                if !stream {
+                    return Ok(HttpResponse::Ok()
+                        .append_header(("X-LLM-Provider", provider))
+                        .json(response));
+                }
+                let speech = response.choices[0].message.content.clone();
+                return Ok(canned::respond(
+                    &request,
+                    &speech.unwrap_or_default(),
+                    ("X-LLM-Provider", &provider),
+                ));
+            }
+            Err(errors) => errors.join("; "),
+        };
+
+        eprintln!(
+            "Structured output for call {} from {} is invalid: {}",
+            call_id, provider, errors
+        );
+        if attempt >= config.max_retries {
+            let error = CustomLlmError::InvalidOutput(errors);
+            if !stream {
+                return Err(error);
+            }
+            let body = format!(
+                "{}{}",
+                sse::error_event(&error),
+                sse::data(&sse::text_chunk(
+                    &response.id,
+                    &response.model,
+                    &env_config.llm.apology_message
+                ))
+            );
+            return Ok(HttpResponse::Ok()
+                .append_header(("Content-Type", "text/event-stream"))
+                .append_header(("X-LLM-Provider", provider))
+                .body(body));
+        }
+
+        attempt += 1;
+        request.stream = Some(false);
+        request.messages.push(assistant_message(content));
+        request.messages.push(system_message(format!(
+            "Your last reply was not valid: {}. Reply again with only the corrected JSON object.",
+            errors
+        )));
+    }
+}
+
+fn parse(
+    content: &str,
+    config: &StructuredOutputConfig,
+) -> Result<(String, Map<String, Value>), Vec<String>> {
+    let value: Value =
+        serde_json::from_str(content).map_err(|e| vec![format!("not valid JSON: {}", e)])?;
+    let mut errors = Vec::new();
+    validate(&config.schema, &value, "$", &mut errors);
+
+    let mut fields = match value {
+        Value::Object(fields) => fields,
+        _ => return Err(vec!["$: expected an object".to_string()]),
+    };
+    match fields.remove(&config.speech_field) {
+        Some(Value::String(speech)) if errors.is_empty() => Ok((speech, fields)),
+        Some(Value::String(_)) => Err(errors),
+        _ => {
+            errors.push(format!(
+                "$.{}: expected the text to speak",
+                config.speech_field
+            ));
+            Err(errors)
+        }
+    }
+}
+
+// Checks the parts of JSON Schema that describe a reply's shape: type, enum,
+// required, properties, additionalProperties and items.
+fn validate(schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+    let types: Vec<&str> = match schema.get("type") {
+        Some(Value::String(name)) => vec![name.as_str()],
+        Some(Value::Array(names)) => names.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    if !types.is_empty() && !types.iter().any(|name| has_type(value, name)) {
+        errors.push(format!("{}: expected {}", path, types.join(" or ")));
+        return;
+    }
+
+    if let Some(Value::Array(options)) = schema.get("enum") {
+        if !options.contains(value) {
+            errors.push(format!(
+                "{}: expected one of {}",
+                path,
+                Value::Array(options.clone())
+            ));
+        }
+    }
+
+    match value {
+        Value::Object(object) => {
+            let required = schema["required"].as_array().into_iter().flatten();
+            for name in required.filter_map(Value::as_str) {
+                if !object.contains_key(name) {
+                    errors.push(format!("{}.{}: is required", path, name));
+                }
+            }
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (name, field) in object {
+                match properties.and_then(|properties| properties.get(name)) {
+                    Some(field_schema) => {
+                        validate(field_schema, field, &format!("{}.{}", path, name), errors)
+                    }
+                    None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                        errors.push(format!("{}.{}: is not allowed", path, name))
+                    }
+                    None => {}
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    validate(item_schema, item, &format!("{}[{}]", path, index), errors);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn system_message(content: String) -> ChatCompletionRequestMessage {
+    ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+        content,
+        role: Role::System,
+        name: None,
+    })
+}
+
+#[allow(deprecated)]
+fn assistant_message(content: String) -> ChatCompletionRequestMessage {
+    ChatCompletionRequestMessage::Assistant(ChatCompletionRequestAssistantMessage {
+        content: Some(content),
+        role: Role::Assistant,
+        name: None,
+        tool_calls: None,
+        function_call: None,
+    })
+}
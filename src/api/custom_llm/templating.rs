@@ -0,0 +1,47 @@
+use crate::types::vapi::CallContext;
+use async_openai::types::{ChatCompletionRequestMessage, CreateChatCompletionRequest};
+use regex::{Captures, Regex};
+use serde_json::Value;
+use std::sync::OnceLock;
+
+// Fills `{{call.customer.name}}`-style placeholders in the system prompts from
+// the call context, so one assistant prompt can be personalised per call.
+// `customer.*` is accepted as a shorthand for `call.customer.*`. Placeholders
+// that do not resolve are left as they are.
+pub fn render_system_prompts(request: &mut CreateChatCompletionRequest, context: &CallContext) {
+    static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+    let placeholder = PLACEHOLDER.get_or_init(|| Regex::new(r"\{\{\s*([\w.]+)\s*\}\}").unwrap());
+
+    let context = match serde_json::to_value(context) {
+        Ok(context) => context,
+        Err(_) => return,
+    };
+
+    for message in request.messages.iter_mut() {
+        if let ChatCompletionRequestMessage::System(message) = message {
+            message.content = placeholder
+                .replace_all(&message.content, |caps: &Captures| {
+                    lookup(&context, &caps[1]).unwrap_or_else(|| caps[0].to_string())
+                })
+                .to_string();
+        }
+    }
+}
+
+pub fn lookup(context: &Value, path: &str) -> Option<String> {
+    let path = if path.starts_with("customer.") {
+        format!("call.{}", path)
+    } else {
+        path.to_string()
+    };
+
+    let mut value = context;
+    for key in path.split('.') {
+        value = value.get(key)?;
+    }
+    match value {
+        Value::Null => None,
+        Value::String(value) => Some(value.clone()),
+        value => Some(value.to_string()),
+    }
+}
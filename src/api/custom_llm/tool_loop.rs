@@ -0,0 +1,332 @@
+use crate::api::custom_llm::{error::CustomLlmError, upstream::Upstream};
+use crate::config::env::ToolsConfig;
+use crate::functions::call_function;
+use async_openai::{
+    error::OpenAIError,
+    types::{
+        ChatCompletionMessageToolCall, ChatCompletionMessageToolCallChunk,
+        ChatCompletionRequestAssistantMessage, ChatCompletionRequestMessage,
+        ChatCompletionRequestToolMessage, ChatCompletionToolType, CreateChatCompletionRequest,
+        CreateChatCompletionResponse, CreateChatCompletionStreamResponse, FinishReason,
+        FunctionCall, Role,
+    },
+};
+use futures::stream::StreamExt;
+use std::collections::BTreeMap;
+
+// The chunks of one streamed turn and the provider that produced them. A
+// stream that breaks partway through keeps what arrived before the error.
+pub struct StreamedTurn {
+    pub provider: String,
+    pub chunks: Vec<CreateChatCompletionStreamResponse>,
+    pub error: Option<OpenAIError>,
+}
+
+// Streams a completion, running any tool calls the model makes ourselves when
+// every requested tool is configured as server-side. Only the chunks of the
+// final round are returned, so Vapi never sees the intermediate tool calls.
+pub async fn stream_with_tools(
+    upstream: &Upstream,
+    mut request: CreateChatCompletionRequest,
+    tools: &ToolsConfig,
+) -> Result<StreamedTurn, CustomLlmError> {
+    let mut iteration = 0;
+    loop {
+        let (provider, mut stream) = upstream.stream(&request).await?;
+        let mut chunks = Vec::new();
+        let mut tool_calls = ToolCallAccumulator::default();
+        let mut finish_reason = None;
+        let mut error = None;
+
+        while let Some(response) = stream.next().await {
+            match response {
+                Ok(ccr) => {
+                    for choice in &ccr.choices {
+                        if let Some(calls) = &choice.delta.tool_calls {
+                            tool_calls.push(calls);
+                        }
+                        if choice.finish_reason.is_some() {
+                            finish_reason = choice.finish_reason;
+                        }
+                    }
+                    chunks.push(ccr);
+                }
+                Err(e) => {
+                    error = Some(e);
+                    break;
+                }
+            }
+        }
+
+        let calls = tool_calls.finish();
+        if error.is_some()
+            || finish_reason != Some(FinishReason::ToolCalls)
+            || iteration >= tools.max_iterations
+            || !runs_on_server(&calls, tools)
+        {
+            return Ok(StreamedTurn {
+                provider,
+                chunks,
+                error,
+            });
+        }
+
+        append_tool_results(&mut request, calls).await;
+        iteration += 1;
+    }
+}
+
+// Non-streaming counterpart of `stream_with_tools`.
+pub async fn create_with_tools(
+    upstream: &Upstream,
+    mut request: CreateChatCompletionRequest,
+    tools: &ToolsConfig,
+) -> Result<(String, CreateChatCompletionResponse), CustomLlmError> {
+    let mut iteration = 0;
+    loop {
+        let (provider, response) = upstream.create(&request).await?;
+        let calls = match response.choices.first() {
+            Some(choice) if choice.finish_reason == Some(FinishReason::ToolCalls) => {
+                choice.message.tool_calls.clone().unwrap_or_default()
+            }
+            _ => return Ok((provider, response)),
+        };
+
+        if iteration >= tools.max_iterations || !runs_on_server(&calls, tools) {
+            return Ok((provider, response));
+        }
+
+        append_tool_results(&mut request, calls).await;
+        iteration += 1;
+    }
+}
+
+fn runs_on_server(calls: &[ChatCompletionMessageToolCall], tools: &ToolsConfig) -> bool {
+    !calls.is_empty()
+        && calls
+            .iter()
+            .all(|call| tools.server_side.contains(&call.function.name))
+}
+
+#[allow(deprecated)]
+async fn append_tool_results(
+    request: &mut CreateChatCompletionRequest,
+    calls: Vec<ChatCompletionMessageToolCall>,
+) {
+    let mut results = Vec::new();
+    for call in &calls {
+        let parameters = serde_json::from_str(&call.function.arguments).unwrap_or_default();
+        let content = call_function(&call.function.name, parameters)
+            .await
+            .unwrap_or_default();
+        results.push(ChatCompletionRequestMessage::Tool(
+            ChatCompletionRequestToolMessage {
+                role: Role::Tool,
+                content,
+                tool_call_id: call.id.clone(),
+            },
+        ));
+    }
+
+    request
+        .messages
+        .push(ChatCompletionRequestMessage::Assistant(
+            ChatCompletionRequestAssistantMessage {
+                content: None,
+                role: Role::Assistant,
+                name: None,
+                tool_calls: Some(calls),
+                function_call: None,
+            },
+        ));
+    request.messages.extend(results);
+}
+
+// Stitches streamed tool call fragments back together by their index.
+#[derive(Default)]
+struct ToolCallAccumulator {
+    calls: BTreeMap<i32, ChatCompletionMessageToolCall>,
+}
+
+impl ToolCallAccumulator {
+    fn push(&mut self, chunks: &[ChatCompletionMessageToolCallChunk]) {
+        for chunk in chunks {
+            let call =
+                self.calls
+                    .entry(chunk.index)
+                    .or_insert_with(|| ChatCompletionMessageToolCall {
+                        id: String::new(),
+                        r#type: ChatCompletionToolType::Function,
+                        function: FunctionCall {
+                            name: String::new(),
+                            arguments: String::new(),
+                        },
+                    });
+            if let Some(id) = &chunk.id {
+                call.id.push_str(id);
+            }
+            if let Some(function) = &chunk.function {
+                if let Some(name) = &function.name {
+                    call.function.name.push_str(name);
+                }
+                if let Some(arguments) = &function.arguments {
+                    call.function.arguments.push_str(arguments);
+                }
+            }
+        }
+    }
+
+    fn finish(self) -> Vec<ChatCompletionMessageToolCall> {
+        self.calls.into_values().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::custom_llm::fake::{chunk, upstream, FakeLlm, Reply};
+    use serde_json::{json, Value};
+    use std::time::Duration;
+
+    fn tool_call(index: i32, id: Option<&str>, name: Option<&str>, arguments: &str) -> Value {
+        let mut function = json!({ "arguments": arguments });
+        if let Some(name) = name {
+            function["name"] = json!(name);
+        }
+        let mut call = json!({ "index": index, "function": function });
+        if let Some(id) = id {
+            call["id"] = json!(id);
+            call["type"] = json!("function");
+        }
+        json!({ "tool_calls": [call] })
+    }
+
+    fn inspiration_call() -> Reply {
+        Reply::Stream(
+            Duration::ZERO,
+            vec![
+                chunk(
+                    tool_call(0, Some("call_1"), Some("getCharacterInspiration"), ""),
+                    None,
+                ),
+                chunk(tool_call(0, None, None, "{\"inspir"), None),
+                chunk(tool_call(0, None, None, "ation\": \"a pirate\"}"), None),
+                chunk(json!({}), Some("tool_calls")),
+            ],
+        )
+    }
+
+    fn request() -> CreateChatCompletionRequest {
+        serde_json::from_value(json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "Inspire me"}],
+            "stream": true,
+        }))
+        .unwrap()
+    }
+
+    fn tools(max_iterations: usize) -> ToolsConfig {
+        ToolsConfig {
+            server_side: vec!["getCharacterInspiration".to_string()],
+            max_iterations,
+        }
+    }
+
+    fn chunks(values: Vec<Value>) -> Vec<ChatCompletionMessageToolCallChunk> {
+        values
+            .into_iter()
+            .map(|value| serde_json::from_value(value["tool_calls"][0].clone()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn tool_calls_are_stitched_together_by_index() {
+        let mut accumulator = ToolCallAccumulator::default();
+        accumulator.push(&chunks(vec![
+            tool_call(0, Some("call_1"), Some("lookup"), "{\"ci"),
+            tool_call(1, Some("call_2"), Some("weather"), ""),
+        ]));
+        accumulator.push(&chunks(vec![tool_call(1, None, None, "{\"day\": 1}")]));
+        accumulator.push(&chunks(vec![tool_call(0, None, None, "ty\": \"Oslo\"}")]));
+
+        let calls: Vec<(String, String, String)> = accumulator
+            .finish()
+            .into_iter()
+            .map(|call| (call.id, call.function.name, call.function.arguments))
+            .collect();
+        assert_eq!(
+            calls,
+            vec![
+                (
+                    "call_1".to_string(),
+                    "lookup".to_string(),
+                    "{\"city\": \"Oslo\"}".to_string()
+                ),
+                (
+                    "call_2".to_string(),
+                    "weather".to_string(),
+                    "{\"day\": 1}".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    async fn server_side_tools_run_before_the_answer_is_streamed() {
+        let llm = FakeLlm::start(vec![
+            inspiration_call(),
+            Reply::Stream(
+                Duration::ZERO,
+                vec![
+                    chunk(json!({"content": "Arr!"}), None),
+                    chunk(json!({}), Some("stop")),
+                ],
+            ),
+        ]);
+        let upstream = upstream(vec![llm.target("tools-answer")], None);
+
+        let turn = stream_with_tools(&upstream, request(), &tools(3))
+            .await
+            .unwrap();
+
+        assert!(turn.error.is_none());
+        assert_eq!(turn.chunks.len(), 2);
+        assert_eq!(
+            turn.chunks[0].choices[0].delta.content.as_deref(),
+            Some("Arr!")
+        );
+        let received = llm.received();
+        assert_eq!(received.len(), 2);
+        let messages = &received[1]["messages"];
+        assert_eq!(
+            messages[1]["tool_calls"][0]["function"]["arguments"],
+            "{\"inspiration\": \"a pirate\"}"
+        );
+        assert_eq!(messages[2]["role"], "tool");
+        assert_eq!(messages[2]["tool_call_id"], "call_1");
+        assert!(messages[2]["content"]
+            .as_str()
+            .unwrap()
+            .contains("placeholder"));
+    }
+
+    #[actix_web::test]
+    async fn tool_rounds_stop_at_max_iterations() {
+        let llm = FakeLlm::start(vec![
+            inspiration_call(),
+            inspiration_call(),
+            inspiration_call(),
+        ]);
+        let upstream = upstream(vec![llm.target("tools-limit")], None);
+
+        let turn = stream_with_tools(&upstream, request(), &tools(1))
+            .await
+            .unwrap();
+
+        assert_eq!(llm.received().len(), 2);
+        assert_eq!(
+            turn.chunks.last().unwrap().choices[0].finish_reason,
+            Some(FinishReason::ToolCalls)
+        );
+    }
+}
@@ -0,0 +1,411 @@
+use crate::api::custom_llm::{error::CustomLlmError, limiter};
+use crate::config::env::{LlmConfig, LlmTarget};
+use actix_web::rt::time::{sleep, timeout};
+use async_openai::{
+    config::OpenAIConfig,
+    error::OpenAIError,
+    types::{
+        ChatCompletionResponseStream, CreateChatCompletionRequest, CreateChatCompletionResponse,
+    },
+    Client,
+};
+use futures::future::{select, Either};
+use futures::stream::{self, StreamExt};
+use std::time::Duration;
+use tokio::sync::OwnedSemaphorePermit;
+
+pub struct Target {
+    pub provider: String,
+    pub model: Option<String>,
+    pub max_concurrency: Option<usize>,
+    pub client: Client<OpenAIConfig>,
+}
+
+impl Target {
+    pub fn from_config(target: &LlmTarget) -> Self {
+        Target {
+            provider: target.provider.clone(),
+            model: target.model.clone(),
+            max_concurrency: target.max_concurrency,
+            client: client(
+                OpenAIConfig::new()
+                    .with_api_base(&target.base_url)
+                    .with_api_key(&target.api_key),
+            ),
+        }
+    }
+
+    // The provider/model pair that is reported as having answered a turn.
+    fn label(&self, request: &CreateChatCompletionRequest) -> String {
+        format!(
+            "{}/{}",
+            self.provider,
+            self.model.as_deref().unwrap_or(&request.model)
+        )
+    }
+
+    fn prepare(&self, request: &CreateChatCompletionRequest) -> CreateChatCompletionRequest {
+        let mut request = request.clone();
+        if let Some(model) = &self.model {
+            request.model = model.clone();
+        }
+        request
+    }
+}
+
+// The ordered chain of providers a completion is attempted against.
+pub struct Upstream {
+    pub targets: Vec<Target>,
+    pub first_token_timeout: Duration,
+    pub hedge_delay: Option<Duration>,
+    pub queue_limit: usize,
+    pub queue_timeout: Duration,
+    pub rate_limit_cooldown: Duration,
+}
+
+impl Upstream {
+    pub fn from_config(config: &LlmConfig) -> Self {
+        Upstream {
+            targets: config
+                .fallback_chain
+                .iter()
+                .map(Target::from_config)
+                .collect(),
+            first_token_timeout: Duration::from_millis(config.first_token_timeout_ms),
+            hedge_delay: config.hedge_delay_ms.map(Duration::from_millis),
+            queue_limit: config.queue_limit,
+            queue_timeout: Duration::from_millis(config.queue_timeout_ms),
+            rate_limit_cooldown: Duration::from_millis(config.rate_limit_cooldown_ms),
+        }
+    }
+
+    // Puts a target ahead of the configured chain, which then only serves as
+    // its fallback. Fallbacks without a model of their own pass on the
+    // request's, which is now the routed target's: those of the same
+    // provider get it explicitly, and those of another provider are dropped,
+    // as that provider would not know the model and fail exactly when the
+    // fallback is needed.
+    pub fn prefer(&mut self, target: Target) {
+        self.targets.retain_mut(|fallback| {
+            if fallback.model.is_some() {
+                return true;
+            }
+            if fallback.provider == target.provider {
+                fallback.model = target.model.clone();
+                return true;
+            }
+            eprintln!(
+                "Skipping fallback {} for {}/{}: give it a model in LLM_FALLBACK_CHAIN, as in {}:<model>",
+                fallback.provider,
+                target.provider,
+                target.model.as_deref().unwrap_or_default(),
+                fallback.provider
+            );
+            false
+        });
+        self.targets.insert(0, target);
+    }
+
+    // Bills every target of the given provider to another API key.
+    pub fn use_api_key(&mut self, provider: &str, api_key: &str) {
+        for target in self
+            .targets
+            .iter_mut()
+            .filter(|target| target.provider == provider)
+        {
+            target.client = client(target.client.config().clone().with_api_key(api_key));
+        }
+    }
+
+    // Client of a given provider, for housekeeping requests that pick their
+    // own model. The chain's own target of that provider is preferred, so the
+    // request is billed to the same API key as the turn.
+    pub fn client_for(&self, provider: &LlmTarget) -> Client<OpenAIConfig> {
+        self.targets
+            .iter()
+            .find(|target| target.provider == provider.provider)
+            .map(|target| target.client.clone())
+            .unwrap_or_else(|| Target::from_config(provider).client)
+    }
+
+    // Opens a stream against the first provider that produces a token in time.
+    // With a hedge delay configured, the next provider in the chain is started
+    // once the delay passes and whichever answers first wins. The chain only
+    // counts as busy if no provider got as far as failing.
+    pub async fn stream(
+        &self,
+        request: &CreateChatCompletionRequest,
+    ) -> Result<(String, ChatCompletionResponseStream), CustomLlmError> {
+        let mut last_error = None;
+        let mut index = 0;
+        while index < self.targets.len() {
+            let primary = &self.targets[index];
+            let hedge = self
+                .hedge_delay
+                .and_then(|delay| self.targets.get(index + 1).map(|target| (delay, target)));
+
+            let result = match hedge {
+                Some((delay, secondary)) => {
+                    index += 2;
+                    self.race(primary, secondary, delay, request).await
+                }
+                None => {
+                    index += 1;
+                    self.first_token(primary, request)
+                        .await
+                        .map(|stream| (primary.label(request), stream))
+                }
+            };
+
+            match result {
+                Ok(answer) => return Ok(answer),
+                Err(e) => keep_worst(&mut last_error, e),
+            }
+        }
+        Err(last_error.unwrap_or_else(no_providers))
+    }
+
+    // Non-streaming requests fall back on errors only, since a full completion
+    // can legitimately take longer than the first token deadline.
+    pub async fn create(
+        &self,
+        request: &CreateChatCompletionRequest,
+    ) -> Result<(String, CreateChatCompletionResponse), CustomLlmError> {
+        let mut last_error = None;
+        for target in &self.targets {
+            let _permit = match self.acquire(target, request).await {
+                Ok(permit) => permit,
+                Err(e) => {
+                    keep_worst(&mut last_error, e);
+                    continue;
+                }
+            };
+            match target.client.chat().create(target.prepare(request)).await {
+                Ok(response) => return Ok((target.label(request), response)),
+                Err(e) => {
+                    eprintln!("Upstream {} failed: {}", target.label(request), e);
+                    keep_worst(&mut last_error, self.failure(target, e));
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(no_providers))
+    }
+
+    // A rate limited provider is as good as busy for this turn.
+    fn failure(&self, target: &Target, error: OpenAIError) -> CustomLlmError {
+        if limiter::rate_limited(&target.provider, &error, self.rate_limit_cooldown) {
+            CustomLlmError::Busy(error.to_string())
+        } else {
+            error.into()
+        }
+    }
+
+    async fn acquire(
+        &self,
+        target: &Target,
+        request: &CreateChatCompletionRequest,
+    ) -> Result<Option<OwnedSemaphorePermit>, CustomLlmError> {
+        limiter::acquire(
+            &target.provider,
+            target.max_concurrency,
+            self.queue_limit,
+            self.queue_timeout,
+        )
+        .await
+        .map_err(|reason| {
+            eprintln!("Upstream {} is busy: {}", target.label(request), reason);
+            CustomLlmError::Busy(reason)
+        })
+    }
+
+    async fn race(
+        &self,
+        primary: &Target,
+        secondary: &Target,
+        delay: Duration,
+        request: &CreateChatCompletionRequest,
+    ) -> Result<(String, ChatCompletionResponseStream), CustomLlmError> {
+        let first = Box::pin(self.first_token(primary, request));
+        let first = match select(first, Box::pin(sleep(delay))).await {
+            Either::Left((Ok(stream), _)) => return Ok((primary.label(request), stream)),
+            Either::Left((Err(_), _)) => {
+                return self
+                    .first_token(secondary, request)
+                    .await
+                    .map(|stream| (secondary.label(request), stream));
+            }
+            Either::Right((_, first)) => first,
+        };
+
+        let second = Box::pin(self.first_token(secondary, request));
+        match select(first, second).await {
+            Either::Left((Ok(stream), _)) => Ok((primary.label(request), stream)),
+            Either::Right((Ok(stream), _)) => Ok((secondary.label(request), stream)),
+            Either::Left((Err(_), second)) => second
+                .await
+                .map(|stream| (secondary.label(request), stream)),
+            Either::Right((Err(_), first)) => {
+                first.await.map(|stream| (primary.label(request), stream))
+            }
+        }
+    }
+
+    // Waits for the first chunk of a stream, then hands back a stream that
+    // replays it before the rest. The provider slot is held until the stream
+    // is dropped.
+    async fn first_token(
+        &self,
+        target: &Target,
+        request: &CreateChatCompletionRequest,
+    ) -> Result<ChatCompletionResponseStream, CustomLlmError> {
+        let permit = self.acquire(target, request).await?;
+        let opened = async {
+            let mut stream = target
+                .client
+                .chat()
+                .create_stream(target.prepare(request))
+                .await?;
+            match stream.next().await {
+                Some(Ok(chunk)) => Ok(stream::once(async { Ok(chunk) }).chain(stream).boxed()),
+                Some(Err(e)) => Err(e),
+                None => Err(OpenAIError::StreamError(
+                    "stream ended before the first token".to_string(),
+                )),
+            }
+        };
+
+        let result = match timeout(self.first_token_timeout, opened).await {
+            Ok(result) => result,
+            Err(_) => Err(OpenAIError::StreamError(format!(
+                "no first token within {}ms",
+                self.first_token_timeout.as_millis()
+            ))),
+        };
+        match result {
+            Ok(stream) => Ok(stream
+                .map(move |chunk| {
+                    let _permit = &permit;
+                    chunk
+                })
+                .boxed()),
+            Err(e) => {
+                eprintln!("Upstream {} failed: {}", target.label(request), e);
+                Err(self.failure(target, e))
+            }
+        }
+    }
+}
+
+// Rate limits are handled by `limiter`, which moves on to the next provider
+// instead of retrying the same one in place.
+fn client(config: OpenAIConfig) -> Client<OpenAIConfig> {
+    Client::with_config(config).with_backoff(
+        backoff::ExponentialBackoffBuilder::new()
+            .with_max_elapsed_time(Some(Duration::ZERO))
+            .build(),
+    )
+}
+
+// Errors from a provider that failed outrank ones from a provider that was
+// too busy to answer.
+fn keep_worst(last_error: &mut Option<CustomLlmError>, error: CustomLlmError) {
+    if last_error.is_none() || !matches!(error, CustomLlmError::Busy(_)) {
+        *last_error = Some(error);
+    }
+}
+
+fn no_providers() -> CustomLlmError {
+    CustomLlmError::Unconfigured("no upstream providers configured".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::custom_llm::fake::{chunk, upstream, FakeLlm, Reply};
+    use actix_web::{http::StatusCode, ResponseError};
+    use serde_json::json;
+
+    fn answer(delay_ms: u64, text: &str) -> Reply {
+        Reply::Stream(
+            Duration::from_millis(delay_ms),
+            vec![chunk(json!({ "content": text }), Some("stop"))],
+        )
+    }
+
+    fn request() -> CreateChatCompletionRequest {
+        serde_json::from_value(json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "Hi"}],
+            "stream": true,
+        }))
+        .unwrap()
+    }
+
+    async fn first_text(stream: &mut ChatCompletionResponseStream) -> String {
+        let chunk = stream.next().await.unwrap().unwrap();
+        chunk.choices[0].delta.content.clone().unwrap_or_default()
+    }
+
+    #[actix_web::test]
+    async fn failed_providers_fall_back_down_the_chain() {
+        let primary = FakeLlm::start(vec![Reply::Status(500)]);
+        let secondary = FakeLlm::start(vec![answer(0, "from fallback")]);
+        let upstream = upstream(
+            vec![
+                primary.target("race-failing"),
+                secondary.target("race-fallback"),
+            ],
+            None,
+        );
+
+        let (provider, mut stream) = upstream.stream(&request()).await.unwrap();
+
+        assert_eq!(provider, "race-fallback/gpt-4o");
+        assert_eq!(first_text(&mut stream).await, "from fallback");
+        assert_eq!(primary.received().len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn a_hedge_answers_when_the_primary_is_slow() {
+        let primary = FakeLlm::start(vec![answer(1000, "from primary")]);
+        let secondary = FakeLlm::start(vec![answer(0, "from hedge")]);
+        let upstream = upstream(
+            vec![primary.target("race-slow"), secondary.target("race-hedge")],
+            Some(Duration::from_millis(50)),
+        );
+
+        let (provider, mut stream) = upstream.stream(&request()).await.unwrap();
+
+        assert_eq!(provider, "race-hedge/gpt-4o");
+        assert_eq!(first_text(&mut stream).await, "from hedge");
+    }
+
+    #[actix_web::test]
+    async fn a_fast_primary_never_starts_the_hedge() {
+        let primary = FakeLlm::start(vec![answer(0, "from primary")]);
+        let secondary = FakeLlm::start(vec![answer(0, "from hedge")]);
+        let upstream = upstream(
+            vec![primary.target("race-fast"), secondary.target("race-idle")],
+            Some(Duration::from_millis(500)),
+        );
+
+        let (provider, mut stream) = upstream.stream(&request()).await.unwrap();
+
+        assert_eq!(provider, "race-fast/gpt-4o");
+        assert_eq!(first_text(&mut stream).await, "from primary");
+        assert!(secondary.received().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn an_empty_chain_is_a_server_error() {
+        let upstream = upstream(Vec::new(), None);
+        let error = match upstream.stream(&request()).await {
+            Err(error) => error,
+            Ok(_) => panic!("streamed without a provider"),
+        };
+        assert_eq!(error.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(error.body()["error"]["type"], "server_error");
+        let error = upstream.create(&request()).await.unwrap_err();
+        assert_eq!(error.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
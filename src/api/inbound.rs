@@ -21,11 +21,12 @@ pub async fn inbound(c: web::Json<Payload>) -> impl Responder {
     let assistant = types::vapi::Assistant {
         name: Some(name),
         model: Some(types::vapi::Model {
-            provider: "openai".to_string(),
-            model: model_name,
+            provider: Some("openai".to_string()),
+            model: Some(model_name),
             temperature: Some(temp),
             system_prompt: Some(system_prompt),
             url: None,
+            other: Default::default(),
             functions: Some(vec![types::vapi::Function {
                 name: "sendEmail".to_string(),
                 description: Some(function_description),
@@ -44,11 +45,12 @@ pub async fn inbound(c: web::Json<Payload>) -> impl Responder {
                     },
                     "required": ["email"]
                 })),
+                other: Default::default(),
             }]),
         }),
         voice: Some(types::vapi::Voice {
             provider: "11labs".to_string(),
-            voice_id: "paula".to_string(),
+            voice_id: Some("paula".to_string()),
             speed: None,
             stability: None,
             similarity_boost: None,
@@ -59,6 +61,7 @@ pub async fn inbound(c: web::Json<Payload>) -> impl Responder {
             voice_guidance: None,
             style_guidance: None,
             text_guidance: None,
+            other: Default::default(),
         }),
         first_message: Some(first_message),
         ..Default::default()
@@ -66,3 +69,29 @@ pub async fn inbound(c: web::Json<Payload>) -> impl Responder {
 
     HttpResponse::Ok().json(json!({"assistant": assistant}))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, App};
+
+    // Vapi's own API is sent camelCase (see `ToVapi`), but this response has
+    // always been snake_case and callers depend on it.
+    #[actix_web::test]
+    async fn the_inbound_assistant_keeps_its_snake_case_fields() {
+        let app = test::init_service(App::new().route("/inbound", web::post().to(inbound))).await;
+        let request = test::TestRequest::post()
+            .uri("/inbound")
+            .set_json(json!({"message": {"type": "hang", "call": {}}}))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+        let assistant = &body["assistant"];
+        assert_eq!(
+            assistant["first_message"],
+            "Hi, I'm Paula, your personal email assistant."
+        );
+        assert!(assistant["model"]["system_prompt"].is_string());
+        assert_eq!(assistant["model"]["functions"][0]["is_async"], false);
+        assert_eq!(assistant["voice"]["voice_id"], "paula");
+    }
+}
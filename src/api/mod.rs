@@ -1,6 +1,12 @@
+pub mod auth;
+pub mod calls;
+pub mod campaign;
 pub mod custom_llm;
 pub mod function_call;
 pub mod inbound;
 pub mod outbound;
+pub mod registry;
+pub mod retry;
 pub mod routes;
+pub mod schedule;
 pub mod webhook;
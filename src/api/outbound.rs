@@ -1,42 +1,475 @@
-use crate::config::env;
-use actix_web::{post, web, HttpResponse, Responder};
-use reqwest::Client;
+use crate::api::calls::lifecycle;
+use crate::api::registry::store as registry;
+use crate::types::vapi::{Assistant, AssistantOverrides, CreateCall, VapiCustomer, Voice};
+use crate::vapi_client::{VapiApi, VapiError};
+use actix_web::{
+    error::JsonPayloadError, http::StatusCode, web, HttpRequest, HttpResponse, ResponseError,
+};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::fmt;
+use std::sync::OnceLock;
 
+// The call is answered either by a stored assistant, `assistantId`, or by a
+// transient one given in full, `assistant`. Either can be personalized for
+// the call with `assistantOverrides`.
 #[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct RequestBody {
-    phoneNumberId: String,
-    assistantId: String,
-    customerNumber: String,
+    phone_number_id: String,
+    assistant_id: Option<String>,
+    assistant: Option<Assistant>,
+    assistant_overrides: Option<AssistantOverrides>,
+    customer_number: String,
+    customer_name: Option<String>,
+    metadata: Option<Map<String, Value>>,
 }
 
-pub async fn outbound(request_body: web::Json<RequestBody>) -> impl Responder {
-    let env_config = env::load_env_config();
+#[derive(Debug)]
+pub enum OutboundError {
+    // The request itself is malformed.
+    InvalidRequest { field: String, message: String },
+    // A phone number or assistant id that Vapi does not know.
+    UnknownId { field: String, id: String },
+    // The number is on the do-not-call list or lacks consent.
+    NotAllowed(String),
+    Vapi(VapiError),
+}
+
+impl fmt::Display for OutboundError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutboundError::InvalidRequest { field, message } => write!(f, "{}: {}", field, message),
+            OutboundError::UnknownId { field, id } => write!(f, "{}: no such id {}", field, id),
+            OutboundError::NotAllowed(reason) => write!(f, "{}", reason),
+            OutboundError::Vapi(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl ResponseError for OutboundError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            OutboundError::InvalidRequest { .. } => StatusCode::BAD_REQUEST,
+            OutboundError::UnknownId { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            OutboundError::NotAllowed(_) => StatusCode::FORBIDDEN,
+            OutboundError::Vapi(VapiError::Api { status, .. }) => {
+                StatusCode::from_u16(*status).unwrap_or(StatusCode::BAD_GATEWAY)
+            }
+            OutboundError::Vapi(_) => StatusCode::BAD_GATEWAY,
+        }
+    }
+
+    // Vapi's own error body is passed on as it is.
+    fn error_response(&self) -> HttpResponse {
+        let body = match self {
+            OutboundError::InvalidRequest { field, message } => {
+                json!({ "error": message, "field": field })
+            }
+            OutboundError::UnknownId { field, .. } => {
+                json!({ "error": self.to_string(), "field": field })
+            }
+            OutboundError::NotAllowed(reason) => {
+                json!({ "error": reason, "field": "customerNumber" })
+            }
+            OutboundError::Vapi(VapiError::Api { body, .. }) => body.clone(),
+            OutboundError::Vapi(e) => json!({ "error": e.to_string() }),
+        };
+        HttpResponse::build(self.status_code()).json(body)
+    }
+}
+
+// Numbers are dialed as given, so they have to be in E.164 form:
+// a "+", the country code and at most 15 digits in all.
+pub fn is_e164(number: &str) -> bool {
+    static E164: OnceLock<Regex> = OnceLock::new();
+    E164.get_or_init(|| Regex::new(r"^\+[1-9]\d{1,14}$").unwrap())
+        .is_match(number)
+}
+
+// A client that retries the request should send the same `Idempotency-Key`
+// header each time, so Vapi places the call only once.
+pub async fn outbound(
+    req: HttpRequest,
+    request_body: web::Json<RequestBody>,
+    vapi: web::Data<dyn VapiApi>,
+) -> Result<HttpResponse, OutboundError> {
+    let request_body = request_body.into_inner();
+    if !is_e164(&request_body.customer_number) {
+        return Err(OutboundError::InvalidRequest {
+            field: "customerNumber".to_string(),
+            message: format!(
+                "{} is not an E.164 number such as +14155550123",
+                request_body.customer_number
+            ),
+        });
+    }
+
+    if request_body.assistant_id.is_some() == request_body.assistant.is_some() {
+        return Err(invalid(
+            "assistantId",
+            "give either assistantId or a transient assistant, not both",
+        ));
+    }
+    if let Some(assistant) = &request_body.assistant {
+        validate_assistant(assistant)?;
+    }
+    if let Some(overrides) = &request_body.assistant_overrides {
+        validate_overrides(overrides)?;
+    }
+
+    registry::check(&request_body.customer_number).map_err(OutboundError::NotAllowed)?;
+
+    let phone_number = vapi.get_phone_number(&request_body.phone_number_id);
+    match &request_body.assistant_id {
+        Some(assistant_id) => {
+            let (phone_number, assistant) =
+                futures::join!(phone_number, vapi.find_assistant(assistant_id));
+            known(phone_number, "phoneNumberId", &request_body.phone_number_id)?;
+            known(assistant, "assistantId", assistant_id)?;
+        }
+        None => known(
+            phone_number.await,
+            "phoneNumberId",
+            &request_body.phone_number_id,
+        )?,
+    }
+
+    let idempotency_key = req
+        .headers()
+        .get("Idempotency-Key")
+        .and_then(|value| value.to_str().ok());
+    let call = vapi
+        .create_call(
+            &CreateCall {
+                assistant_id: request_body.assistant_id,
+                assistant: request_body.assistant,
+                assistant_overrides: request_body.assistant_overrides,
+                phone_number_id: Some(request_body.phone_number_id),
+                customer: Some(VapiCustomer {
+                    number: Some(request_body.customer_number),
+                    name: request_body.customer_name,
+                }),
+                metadata: request_body.metadata.map(Value::Object),
+                ..Default::default()
+            },
+            idempotency_key,
+        )
+        .await
+        .map_err(OutboundError::Vapi)?;
+    lifecycle::track(&call, "created");
+    Ok(HttpResponse::Ok().json(call))
+}
+
+// Serde has already checked the shapes; these are the values that parse but
+// that Vapi would still refuse.
+fn validate_assistant(assistant: &Assistant) -> Result<(), OutboundError> {
+    if assistant.id.is_some() {
+        return Err(invalid(
+            "assistant.id",
+            "a transient assistant has no id; use assistantId for a stored one",
+        ));
+    }
+    if assistant.model.is_none() {
+        return Err(invalid(
+            "assistant.model",
+            "a transient assistant needs a model",
+        ));
+    }
+    if assistant.voice.is_none() {
+        return Err(invalid(
+            "assistant.voice",
+            "a transient assistant needs a voice",
+        ));
+    }
+    if let Some(model) = &assistant.model {
+        if is_blank(&model.provider) || is_blank(&model.model) {
+            return Err(invalid(
+                "assistant.model",
+                "needs both a provider and a model",
+            ));
+        }
+        validate_temperature("assistant", model.temperature)?;
+    }
+    validate_voice("assistant", assistant.voice.as_ref())
+}
+
+fn validate_overrides(overrides: &AssistantOverrides) -> Result<(), OutboundError> {
+    if overrides.first_message.as_deref() == Some("") {
+        return Err(invalid(
+            "assistantOverrides.firstMessage",
+            "must not be empty; leave it out to keep the assistant's",
+        ));
+    }
+    if let Some(variables) = &overrides.variable_values {
+        if let Some((name, _)) = variables
+            .iter()
+            .find(|(_, value)| value.is_object() || value.is_array())
+        {
+            return Err(invalid(
+                &format!("assistantOverrides.variableValues.{}", name),
+                "must be a string, number or boolean",
+            ));
+        }
+    }
+    // Any part of the model may be overridden, but not with nothing.
+    if let Some(model) = &overrides.model {
+        if model.provider.as_deref() == Some("") || model.model.as_deref() == Some("") {
+            return Err(invalid(
+                "assistantOverrides.model",
+                "provider and model must not be empty; leave them out to keep the assistant's",
+            ));
+        }
+        validate_temperature("assistantOverrides", model.temperature)?;
+    }
+    validate_voice("assistantOverrides", overrides.voice.as_ref())
+}
+
+fn validate_temperature(prefix: &str, temperature: Option<f32>) -> Result<(), OutboundError> {
+    match temperature {
+        Some(temperature) if !(0.0..=2.0).contains(&temperature) => Err(invalid(
+            &format!("{}.model.temperature", prefix),
+            "must be between 0 and 2",
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn validate_voice(prefix: &str, voice: Option<&Voice>) -> Result<(), OutboundError> {
+    if let Some(voice) = voice {
+        if voice.provider.is_empty() || is_blank(&voice.voice_id) {
+            return Err(invalid(
+                &format!("{}.voice", prefix),
+                "needs both a provider and a voiceId",
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn is_blank(value: &Option<String>) -> bool {
+    value.as_deref().unwrap_or_default().is_empty()
+}
 
-    // let request_body_bytes = serde_json::to_vec(&request_body.into_inner()).unwrap();
+fn invalid(field: &str, message: &str) -> OutboundError {
+    OutboundError::InvalidRequest {
+        field: field.to_string(),
+        message: message.to_string(),
+    }
+}
+
+fn known<T>(lookup: Result<T, VapiError>, field: &str, id: &str) -> Result<(), OutboundError> {
+    match lookup {
+        Ok(_) => Ok(()),
+        Err(e) if e.is_not_found() => Err(OutboundError::UnknownId {
+            field: field.to_string(),
+            id: id.to_string(),
+        }),
+        Err(e) => Err(OutboundError::Vapi(e)),
+    }
+}
+
+// Bodies that do not parse get a JSON 400 naming the problem instead of
+// actix's plain text one.
+pub fn json_error_handler(error: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    OutboundError::InvalidRequest {
+        field: "body".to_string(),
+        message: error.to_string(),
+    }
+    .into()
+}
 
-    let request_body_bytes = serde_json::to_vec(&request_body).unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vapi_client::fake::FakeVapi;
+    use actix_web::{test, App};
+    use std::sync::Arc;
 
-    let client = Client::new();
-    let mut req = client
-        .post(format!("{}/call/phone", env_config.vapi.base_url))
-        .header("Content-Type", "application/json")
-        .header(
-            "Authorization",
-            format!("Bearer {}", env_config.vapi.api_key),
+    async fn post(vapi: Arc<FakeVapi>, key: Option<&str>, body: Value) -> (StatusCode, Value) {
+        let vapi: Arc<dyn VapiApi> = vapi;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::from(vapi))
+                .app_data(web::JsonConfig::default().error_handler(json_error_handler))
+                .route("/outbound", web::post().to(outbound)),
         )
-        .body(request_body_bytes)
-        .build()
-        .unwrap();
+        .await;
+        let mut request = test::TestRequest::post().uri("/outbound").set_json(body);
+        if let Some(key) = key {
+            request = request.insert_header(("Idempotency-Key", key));
+        }
+        let response = test::call_service(&app, request.to_request()).await;
+        let status = response.status();
+        (status, test::read_body_json(response).await)
+    }
+
+    #[actix_web::test]
+    async fn transient_assistants_are_sent_to_vapi_in_camel_case() {
+        let vapi = Arc::new(FakeVapi::new().with_phone_number("phone-1"));
+        let (status, call) = post(
+            vapi.clone(),
+            None,
+            json!({
+                "phoneNumberId": "phone-1",
+                "customerNumber": "+15550000101",
+                "assistant": {
+                    "firstMessage": "Hello",
+                    "model": {
+                        "provider": "openai",
+                        "model": "gpt-4o",
+                        "systemPrompt": "Be brief",
+                        "functions": [{
+                            "name": "book",
+                            "isAsync": true,
+                            "parameters": {"properties": {"start_time": {"type": "string"}}}
+                        }]
+                    },
+                    "voice": {"provider": "11labs", "voiceId": "paula"}
+                }
+            }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{}", call);
+        assert_eq!(call["id"], "call-1");
+
+        let sent = &vapi.sent()[0];
+        let assistant = &sent["assistant"];
+        assert_eq!(sent["phoneNumberId"], "phone-1");
+        assert_eq!(assistant["firstMessage"], "Hello");
+        assert_eq!(assistant["model"]["systemPrompt"], "Be brief");
+        assert_eq!(assistant["model"]["functions"][0]["isAsync"], true);
+        assert_eq!(assistant["voice"]["voiceId"], "paula");
+        // Keys inside the function's schema are the caller's, not renamed.
+        assert!(
+            assistant["model"]["functions"][0]["parameters"]["properties"]["start_time"]
+                .is_object()
+        );
+    }
+
+    #[actix_web::test]
+    async fn transient_assistants_keep_fields_the_server_does_not_model() {
+        let vapi = Arc::new(FakeVapi::new().with_phone_number("phone-1"));
+        let mut assistant: Value =
+            serde_json::from_str(include_str!("../../tests/fixtures/assistant.json")).unwrap();
+        for stored_only in [
+            "id",
+            "orgId",
+            "createdAt",
+            "updatedAt",
+            "isServerUrlSecretSet",
+        ] {
+            assistant.as_object_mut().unwrap().remove(stored_only);
+        }
+        let (status, call) = post(
+            vapi.clone(),
+            None,
+            json!({
+                "phoneNumberId": "phone-1",
+                "customerNumber": "+15550000105",
+                "assistant": assistant,
+            }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{}", call);
+
+        let sent = &vapi.sent()[0]["assistant"];
+        assert_eq!(sent["clientMessages"], assistant["clientMessages"]);
+        assert_eq!(sent["analysisPlan"], assistant["analysisPlan"]);
+        assert_eq!(sent["voicemailDetection"], assistant["voicemailDetection"]);
+        assert_eq!(sent["model"]["messages"], assistant["model"]["messages"]);
+        assert_eq!(sent["model"]["tools"], assistant["model"]["tools"]);
+        assert_eq!(sent["voice"]["model"], assistant["voice"]["model"]);
+        assert_eq!(sent["transcriber"]["language"], "en");
+    }
+
+    #[actix_web::test]
+    async fn partial_overrides_and_other_vapi_fields_are_passed_on() {
+        let vapi = Arc::new(
+            FakeVapi::new()
+                .with_phone_number("phone-1")
+                .with_assistant("assistant-1"),
+        );
+        let body = |temperature: f32| {
+            json!({
+                "phoneNumberId": "phone-1",
+                "assistantId": "assistant-1",
+                "customerNumber": "+15550000103",
+                "assistantOverrides": {
+                    "model": {"temperature": temperature},
+                    "endCallMessage": "Goodbye",
+                    "recordingEnabled": false,
+                }
+            })
+        };
+
+        let (status, error) = post(vapi.clone(), None, body(3.0)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["field"], "assistantOverrides.model.temperature");
+
+        let (status, call) = post(vapi.clone(), None, body(0.25)).await;
+        assert_eq!(status, StatusCode::OK, "{}", call);
+        let overrides = &vapi.sent()[0]["assistantOverrides"];
+        assert_eq!(overrides["model"]["temperature"], 0.25);
+        assert!(overrides["model"]["provider"].is_null());
+        assert_eq!(overrides["endCallMessage"], "Goodbye");
+        assert_eq!(overrides["recordingEnabled"], false);
+    }
 
-    let resp = client.execute(req).await.unwrap();
-    let status_code = resp.status();
+    #[actix_web::test]
+    async fn stored_assistants_only_need_to_exist() {
+        let stored: Value =
+            serde_json::from_str(include_str!("../../tests/fixtures/assistant.json")).unwrap();
+        // A field in a shape `Assistant` does not expect must not matter.
+        let mut newer = stored.clone();
+        newer["id"] = json!("assistant-newer");
+        newer["keywords"] = json!({ "boost": ["Paula"] });
+        let vapi = Arc::new(
+            FakeVapi::new()
+                .with_phone_number("phone-1")
+                .with_assistant_json(stored.clone())
+                .with_assistant_json(newer),
+        );
 
-    if !status_code.is_success() {
-        return HttpResponse::InternalServerError()
-            .json(format!("HTTP error! status: {}", status_code));
+        for assistant_id in [stored["id"].as_str().unwrap(), "assistant-newer"] {
+            let (status, call) = post(
+                vapi.clone(),
+                None,
+                json!({
+                    "phoneNumberId": "phone-1",
+                    "assistantId": assistant_id,
+                    "customerNumber": "+15550000104",
+                }),
+            )
+            .await;
+            assert_eq!(status, StatusCode::OK, "{}", call);
+            assert_eq!(call["assistantId"], assistant_id);
+        }
     }
 
-    let data: serde_json::Value = resp.json().await.unwrap();
-    HttpResponse::Ok().json(data)
+    #[actix_web::test]
+    async fn unknown_ids_and_retries_are_handled_before_dialing_twice() {
+        let vapi = Arc::new(
+            FakeVapi::new()
+                .with_phone_number("phone-1")
+                .with_assistant("assistant-1"),
+        );
+        let body = |assistant_id: &str| {
+            json!({
+                "phoneNumberId": "phone-1",
+                "assistantId": assistant_id,
+                "customerNumber": "+15550000102",
+            })
+        };
+
+        let (status, error) = post(vapi.clone(), None, body("assistant-2")).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(error["field"], "assistantId");
+
+        let (_, first) = post(vapi.clone(), Some("retry-1"), body("assistant-1")).await;
+        let (_, second) = post(vapi.clone(), Some("retry-1"), body("assistant-1")).await;
+        assert_eq!(first["id"], second["id"]);
+        assert_eq!(vapi.sent().len(), 1);
+    }
 }
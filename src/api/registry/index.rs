@@ -0,0 +1,141 @@
+use crate::api::outbound::is_e164;
+use crate::api::registry::store::{self, Consent, DoNotCall, Entries};
+use crate::config::env;
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Debug, Deserialize)]
+pub struct AddDoNotCall {
+    number: String,
+    reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddConsent {
+    number: String,
+    source: String,
+    granted_at: Option<DateTime<Utc>>,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+pub async fn list_do_not_call() -> HttpResponse {
+    HttpResponse::Ok().json(store::registry().export().do_not_call)
+}
+
+pub async fn add_do_not_call(body: web::Json<AddDoNotCall>) -> HttpResponse {
+    let body = body.into_inner();
+    if !is_e164(&body.number) {
+        return invalid_number(&body.number);
+    }
+    let entry = DoNotCall {
+        number: body.number.clone(),
+        reason: body.reason,
+        source: "manual".to_string(),
+        call_id: None,
+        added_at: Utc::now(),
+    };
+    let mut registry = store::registry();
+    registry.do_not_call.insert(body.number, entry.clone());
+    registry.save();
+    HttpResponse::Created().json(entry)
+}
+
+pub async fn remove_do_not_call(number: web::Path<String>) -> HttpResponse {
+    let mut registry = store::registry();
+    match registry.do_not_call.remove(number.as_str()) {
+        Some(entry) => {
+            registry.save();
+            HttpResponse::Ok().json(entry)
+        }
+        None => not_found(),
+    }
+}
+
+pub async fn list_consents() -> HttpResponse {
+    HttpResponse::Ok().json(store::registry().export().consents)
+}
+
+// Recording a consent replaces any earlier one for the number.
+pub async fn add_consent(body: web::Json<AddConsent>) -> HttpResponse {
+    let body = body.into_inner();
+    if !is_e164(&body.number) {
+        return invalid_number(&body.number);
+    }
+    let consent = Consent {
+        number: body.number.clone(),
+        source: body.source,
+        granted_at: body.granted_at.unwrap_or_else(Utc::now),
+        expires_at: body.expires_at,
+    };
+    let mut registry = store::registry();
+    registry.consents.insert(body.number, consent.clone());
+    registry.save();
+    HttpResponse::Created().json(consent)
+}
+
+pub async fn remove_consent(number: web::Path<String>) -> HttpResponse {
+    let mut registry = store::registry();
+    match registry.consents.remove(number.as_str()) {
+        Some(consent) => {
+            registry.save();
+            HttpResponse::Ok().json(consent)
+        }
+        None => not_found(),
+    }
+}
+
+// Whether a number may be called right now, and if not, why.
+pub async fn check(number: web::Path<String>) -> HttpResponse {
+    let consent_required = env::load_env_config().registry.consent_required;
+    let refusal = store::registry().refusal(&number, consent_required);
+    HttpResponse::Ok().json(json!({
+        "number": number.as_str(),
+        "allowed": refusal.is_none(),
+        "reason": refusal,
+    }))
+}
+
+pub async fn export() -> HttpResponse {
+    HttpResponse::Ok().json(store::registry().export())
+}
+
+// Takes an export from this or another server. Entries for numbers already
+// in the registry replace the ones there; nothing is removed.
+pub async fn import(body: web::Json<Entries>) -> HttpResponse {
+    let entries = body.into_inner();
+    let invalid: Vec<&String> = entries
+        .do_not_call
+        .iter()
+        .map(|entry| &entry.number)
+        .chain(entries.consents.iter().map(|consent| &consent.number))
+        .filter(|number| !is_e164(number))
+        .collect();
+    if !invalid.is_empty() {
+        return HttpResponse::BadRequest().json(json!({
+            "error": "not E.164 numbers",
+            "numbers": invalid,
+        }));
+    }
+    let counts = json!({
+        "doNotCall": entries.do_not_call.len(),
+        "consents": entries.consents.len(),
+    });
+    let mut registry = store::registry();
+    registry.import(entries);
+    registry.save();
+    HttpResponse::Ok().json(json!({ "imported": counts }))
+}
+
+fn invalid_number(number: &str) -> HttpResponse {
+    HttpResponse::BadRequest().json(json!({
+        "error": format!("{} is not an E.164 number", number),
+        "field": "number",
+    }))
+}
+
+fn not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(json!({ "error": "the number is not in the registry" }))
+}
@@ -0,0 +1,3 @@
+pub mod index;
+pub mod opt_out;
+pub mod store;
@@ -0,0 +1,72 @@
+use crate::api::registry::store::{self, DoNotCall};
+use crate::config::env::RegistryConfig;
+use crate::types::vapi::EndOfCallReportPayload;
+use chrono::Utc;
+use regex::RegexBuilder;
+
+// Puts the customer on the do-not-call list when they asked not to be called
+// again during the call. Only the customer's side of the conversation is
+// searched, so the assistant repeating the phrase does not count.
+pub fn from_report(config: &RegistryConfig, report: &EndOfCallReportPayload) {
+    let number = match report
+        .call
+        .customer
+        .as_ref()
+        .and_then(|customer| customer.number.as_deref())
+    {
+        Some(number) => number,
+        None => return,
+    };
+
+    let customer_lines: Vec<&str> = report
+        .messages
+        .iter()
+        .filter(|message| message.role == "user")
+        .filter_map(|message| message.message.as_deref())
+        .collect();
+    let said = if customer_lines.is_empty() {
+        // Older reports only have the transcript, one "User: ..." line per turn.
+        report
+            .transcript
+            .lines()
+            .filter_map(|line| line.strip_prefix("User:"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    } else {
+        customer_lines.join("\n")
+    };
+
+    let phrase = config.stop_phrases.iter().find(|phrase| {
+        match RegexBuilder::new(phrase).case_insensitive(true).build() {
+            Ok(pattern) => pattern.is_match(&said),
+            Err(e) => {
+                eprintln!("Ignoring invalid stop phrase {}: {}", phrase, e);
+                false
+            }
+        }
+    });
+    let phrase = match phrase {
+        Some(phrase) => phrase,
+        None => return,
+    };
+
+    let mut registry = store::registry();
+    if registry.do_not_call.contains_key(number) {
+        return;
+    }
+    println!(
+        "Adding {} to the do-not-call list after they said \"{}\"",
+        number, phrase
+    );
+    registry.do_not_call.insert(
+        number.to_string(),
+        DoNotCall {
+            number: number.to_string(),
+            reason: Some(format!("asked to stop during the call (\"{}\")", phrase)),
+            source: "transcript".to_string(),
+            call_id: report.call.id.clone(),
+            added_at: Utc::now(),
+        },
+    );
+    registry.save();
+}
@@ -0,0 +1,143 @@
+use crate::config::env;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DoNotCall {
+    pub number: String,
+    pub reason: Option<String>,
+    // "manual", "import" or "transcript".
+    #[serde(default = "imported")]
+    pub source: String,
+    pub call_id: Option<String>,
+    #[serde(default = "Utc::now")]
+    pub added_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Consent {
+    pub number: String,
+    // Where the customer opted in, such as "web-form" or "sms-keyword".
+    pub source: String,
+    #[serde(default = "Utc::now")]
+    pub granted_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+fn imported() -> String {
+    "import".to_string()
+}
+
+// The whole registry, in the form it is saved, exported and imported in.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entries {
+    #[serde(default)]
+    pub do_not_call: Vec<DoNotCall>,
+    #[serde(default)]
+    pub consents: Vec<Consent>,
+}
+
+// Kept in memory by number and written to `REGISTRY_FILE` on every change.
+pub struct Registry {
+    file: String,
+    pub do_not_call: BTreeMap<String, DoNotCall>,
+    pub consents: BTreeMap<String, Consent>,
+}
+
+pub fn registry() -> MutexGuard<'static, Registry> {
+    static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+    REGISTRY
+        .get_or_init(|| Mutex::new(Registry::load(env::load_env_config().registry.file)))
+        .lock()
+        .unwrap()
+}
+
+impl Registry {
+    fn load(file: String) -> Self {
+        let entries: Entries = match fs::read_to_string(&file) {
+            Ok(text) => serde_json::from_str(&text).unwrap_or_else(|e| {
+                eprintln!("Ignoring invalid registry file {}: {}", file, e);
+                Entries::default()
+            }),
+            Err(_) => Entries::default(),
+        };
+        let mut registry = Registry {
+            file,
+            do_not_call: BTreeMap::new(),
+            consents: BTreeMap::new(),
+        };
+        registry.import(entries);
+        registry
+    }
+
+    pub fn import(&mut self, entries: Entries) {
+        for entry in entries.do_not_call {
+            self.do_not_call.insert(entry.number.clone(), entry);
+        }
+        for consent in entries.consents {
+            self.consents.insert(consent.number.clone(), consent);
+        }
+    }
+
+    pub fn export(&self) -> Entries {
+        Entries {
+            do_not_call: self.do_not_call.values().cloned().collect(),
+            consents: self.consents.values().cloned().collect(),
+        }
+    }
+
+    // Written to a temporary file first so a crash can't leave half a file.
+    pub fn save(&self) {
+        let temp = format!("{}.tmp", self.file);
+        let result = serde_json::to_string_pretty(&self.export())
+            .map_err(|e| e.to_string())
+            .and_then(|text| fs::write(&temp, text).map_err(|e| e.to_string()))
+            .and_then(|_| fs::rename(&temp, &self.file).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            eprintln!("Failed to save the registry to {}: {}", self.file, e);
+        }
+    }
+
+    // Why a number may not be called, if it may not. A number on the
+    // do-not-call list is never called; when consent is required it also
+    // needs a consent that has not expired.
+    pub fn refusal(&self, number: &str, consent_required: bool) -> Option<String> {
+        if let Some(entry) = self.do_not_call.get(number) {
+            return Some(format!(
+                "{} is on the do-not-call list ({})",
+                number, entry.source
+            ));
+        }
+        if !consent_required {
+            return None;
+        }
+        match self.consents.get(number) {
+            None => Some(format!("{} has not consented to calls", number)),
+            Some(Consent {
+                expires_at: Some(expires_at),
+                ..
+            }) if *expires_at <= Utc::now() => {
+                Some(format!("{}'s consent expired at {}", number, expires_at))
+            }
+            Some(_) => None,
+        }
+    }
+}
+
+// Checked right before every outbound call is placed.
+pub fn check(number: &str) -> Result<(), String> {
+    let consent_required = env::load_env_config().registry.consent_required;
+    match registry().refusal(number, consent_required) {
+        Some(reason) => {
+            println!("Not calling {}: {}", number, reason);
+            Err(reason)
+        }
+        None => Ok(()),
+    }
+}
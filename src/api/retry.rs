@@ -0,0 +1,96 @@
+use crate::config::env::RetryConfig;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+// How often to try a customer whose calls end in voicemail, unanswered or
+// busy, and how long to wait between tries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryPolicy {
+    // Calls placed in all, counting the first.
+    pub max_attempts: u32,
+    pub spacing_secs: i64,
+    // Left on voicemail by retries instead of the assistant's own message,
+    // so a customer who was already left one hears something new.
+    pub voicemail_message: Option<String>,
+}
+
+// Calls saved before retries existed were only ever tried once.
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            spacing_secs: 0,
+            voicemail_message: None,
+        }
+    }
+}
+
+impl From<&RetryConfig> for RetryPolicy {
+    fn from(config: &RetryConfig) -> Self {
+        RetryPolicy {
+            max_attempts: config.max_attempts,
+            spacing_secs: config.spacing_secs,
+            voicemail_message: config.voicemail_message.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attempt {
+    pub call_id: String,
+    pub ended_reason: String,
+    pub ended_at: DateTime<Utc>,
+}
+
+pub fn is_unanswered(ended_reason: &str) -> bool {
+    matches!(
+        ended_reason,
+        "customer-did-not-answer" | "customer-busy" | "voicemail" | "no-answer"
+    )
+}
+
+impl RetryPolicy {
+    // Policies given in a request, named by their JSON fields under `field`.
+    pub fn validate(&self, field: &str) -> Result<(), String> {
+        if self.max_attempts == 0 {
+            return Err(format!(
+                "{}.maxAttempts must be at least 1, counting the first call",
+                field
+            ));
+        }
+        if self.spacing_secs < 0 {
+            return Err(format!("{}.spacingSecs must not be negative", field));
+        }
+        Ok(())
+    }
+
+    // When to call again after the last of `attempts` ended, if at all.
+    pub fn retry_at(&self, attempts: &[Attempt]) -> Option<DateTime<Utc>> {
+        let last = attempts.last()?;
+        if !is_unanswered(&last.ended_reason) || attempts.len() as u32 >= self.max_attempts {
+            return None;
+        }
+        Some(last.ended_at + Duration::seconds(self.spacing_secs))
+    }
+
+    // The voicemail message for the given attempt, counting from 1.
+    pub fn voicemail_message(&self, attempt: usize) -> Option<String> {
+        if attempt > 1 {
+            self.voicemail_message.clone()
+        } else {
+            None
+        }
+    }
+}
+
+// The idempotency key of each attempt, so that retrying a request never
+// places the same attempt twice while later attempts still go through.
+pub fn idempotency_key(base: &str, attempt: usize) -> String {
+    if attempt > 1 {
+        format!("{}-attempt-{}", base, attempt)
+    } else {
+        base.to_string()
+    }
+}
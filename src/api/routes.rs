@@ -1,38 +1,244 @@
+use crate::api::auth::{index as auth, keys, middleware::Require};
+use crate::api::calls::index as calls;
+use crate::api::campaign::index as campaign;
 use crate::api::custom_llm::basic;
+use crate::api::custom_llm::cache;
+use crate::api::custom_llm::error;
 use crate::api::custom_llm::openai_advanced;
 use crate::api::custom_llm::openai_sse;
+use crate::api::custom_llm::session;
 use crate::api::function_call::basic as basic_functions;
 use crate::api::function_call::rag;
 use crate::api::inbound;
 use crate::api::outbound;
+use crate::api::registry::index as registry;
+use crate::api::schedule::index as schedule;
 use crate::api::webhook;
 use actix_web::web;
 
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/api")
-            .service(web::resource("/inbound").route(web::post().to(inbound::inbound)))
-            .service(web::resource("/outbound").route(web::post().to(outbound::outbound)))
+            .service(
+                web::resource("/inbound").route(
+                    web::post()
+                        .to(inbound::inbound)
+                        .wrap(Require(keys::WEBHOOK)),
+                ),
+            )
+            .service(
+                web::resource("/outbound")
+                    .app_data(
+                        web::JsonConfig::default().error_handler(outbound::json_error_handler),
+                    )
+                    .route(
+                        web::post()
+                            .to(outbound::outbound)
+                            .wrap(Require("calls:create")),
+                    ),
+            )
+            .service(
+                web::scope("/auth")
+                    .wrap(Require("admin"))
+                    .service(
+                        web::resource("/keys")
+                            .route(web::get().to(auth::list_keys))
+                            .route(web::post().to(auth::create_key)),
+                    )
+                    .service(web::resource("/keys/{id}").route(web::delete().to(auth::revoke_key)))
+                    .service(web::resource("/audit").route(web::get().to(auth::audit_trail))),
+            )
+            .service(
+                web::scope("/calls")
+                    .service(
+                        web::resource("")
+                            .route(web::get().to(calls::list).wrap(Require("calls:read"))),
+                    )
+                    .service(
+                        web::resource("/{id}")
+                            .route(web::get().to(calls::get).wrap(Require("calls:read"))),
+                    ),
+            )
+            .service(
+                web::scope("/campaigns")
+                    .service(
+                        web::resource("")
+                            .route(web::get().to(campaign::list).wrap(Require("calls:read")))
+                            .route(
+                                web::post()
+                                    .to(campaign::create)
+                                    .wrap(Require("calls:create")),
+                            ),
+                    )
+                    .service(
+                        web::resource("/{id}")
+                            .route(web::get().to(campaign::get).wrap(Require("calls:read"))),
+                    )
+                    .service(
+                        web::resource("/{id}/pause").route(
+                            web::post()
+                                .to(campaign::pause)
+                                .wrap(Require("calls:create")),
+                        ),
+                    )
+                    .service(
+                        web::resource("/{id}/resume").route(
+                            web::post()
+                                .to(campaign::resume)
+                                .wrap(Require("calls:create")),
+                        ),
+                    )
+                    .service(
+                        web::resource("/{id}/cancel").route(
+                            web::post()
+                                .to(campaign::cancel)
+                                .wrap(Require("calls:create")),
+                        ),
+                    ),
+            )
+            .service(
+                web::scope("/registry")
+                    .service(
+                        web::resource("/do-not-call")
+                            .route(
+                                web::get()
+                                    .to(registry::list_do_not_call)
+                                    .wrap(Require("admin")),
+                            )
+                            .route(
+                                web::post()
+                                    .to(registry::add_do_not_call)
+                                    .wrap(Require("admin")),
+                            ),
+                    )
+                    .service(
+                        web::resource("/do-not-call/{number}").route(
+                            web::delete()
+                                .to(registry::remove_do_not_call)
+                                .wrap(Require("admin")),
+                        ),
+                    )
+                    .service(
+                        web::resource("/consents")
+                            .route(
+                                web::get()
+                                    .to(registry::list_consents)
+                                    .wrap(Require("admin")),
+                            )
+                            .route(web::post().to(registry::add_consent).wrap(Require("admin"))),
+                    )
+                    .service(
+                        web::resource("/consents/{number}").route(
+                            web::delete()
+                                .to(registry::remove_consent)
+                                .wrap(Require("admin")),
+                        ),
+                    )
+                    .service(
+                        web::resource("/check/{number}")
+                            .route(web::get().to(registry::check).wrap(Require("calls:read"))),
+                    )
+                    .service(
+                        web::resource("/export")
+                            .route(web::get().to(registry::export).wrap(Require("admin"))),
+                    )
+                    .service(
+                        web::resource("/import")
+                            .route(web::post().to(registry::import).wrap(Require("admin"))),
+                    ),
+            )
+            .service(
+                web::scope("/scheduled-calls")
+                    .service(
+                        web::resource("")
+                            .route(web::get().to(schedule::list).wrap(Require("calls:read")))
+                            .route(
+                                web::post()
+                                    .to(schedule::create)
+                                    .wrap(Require("calls:create")),
+                            ),
+                    )
+                    .service(
+                        web::resource("/{id}")
+                            .route(web::get().to(schedule::get).wrap(Require("calls:read")))
+                            .route(
+                                web::delete()
+                                    .to(schedule::cancel)
+                                    .wrap(Require("calls:create")),
+                            ),
+                    ),
+            )
             .service(
                 web::scope("/functions")
+                    .wrap(Require(keys::WEBHOOK))
                     .service(web::resource("/basic").route(web::post().to(basic_functions::basic)))
                     .service(web::resource("/rag").route(web::post().to(rag::rag))),
             )
             .service(
                 web::scope("/custom-llm")
+                    .app_data(web::JsonConfig::default().error_handler(error::json_error_handler))
+                    .service(
+                        web::resource("/cache/stats")
+                            .route(web::get().to(cache::stats).wrap(Require("admin"))),
+                    )
+                    .service(
+                        web::resource("/sessions/{call_id}")
+                            .route(web::get().to(session::session).wrap(Require("calls:read"))),
+                    )
                     .service(
                         web::resource("/basic/chat/completions")
-                            .route(web::post().to(basic::basic)),
+                            .route(web::post().to(basic::basic).wrap(Require(keys::WEBHOOK))),
                     )
                     .service(
-                        web::resource("/openai-sse/chat/completions")
-                            .route(web::post().to(openai_sse::openai_sse)),
+                        web::resource("/openai-sse/chat/completions").route(
+                            web::post()
+                                .to(openai_sse::openai_sse)
+                                .wrap(Require(keys::WEBHOOK)),
+                        ),
                     )
                     .service(
-                        web::resource("/openai-advanced/chat/completions")
-                            .route(web::post().to(openai_advanced::openai_advanced)),
+                        web::resource("/openai-advanced/chat/completions").route(
+                            web::post()
+                                .to(openai_advanced::openai_advanced)
+                                .wrap(Require(keys::WEBHOOK)),
+                        ),
                     ),
             )
-            .service(web::resource("/webhook").route(web::post().to(webhook::index::webhook))),
+            .service(
+                web::resource("/webhook").route(
+                    web::post()
+                        .to(webhook::index::webhook)
+                        .wrap(Require(keys::WEBHOOK)),
+                ),
+            ),
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::StatusCode, test, App};
+    use serde_json::json;
+
+    #[actix_web::test]
+    async fn every_route_vapi_calls_needs_the_vapi_secret() {
+        let app = test::init_service(App::new().configure(config)).await;
+        for path in [
+            "/api/webhook",
+            "/api/inbound",
+            "/api/functions/basic",
+            "/api/functions/rag",
+            "/api/custom-llm/basic/chat/completions",
+            "/api/custom-llm/openai-sse/chat/completions",
+            "/api/custom-llm/openai-advanced/chat/completions",
+        ] {
+            let request = test::TestRequest::post()
+                .uri(path)
+                .insert_header(("X-Vapi-Secret", "guess"))
+                .set_json(json!({}))
+                .to_request();
+            let response = test::call_service(&app, request).await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{}", path);
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use crate::config::env::CallingWindow;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+// The earliest time at or after `after` that the calling window allows in the
+// customer's time zone, looking up to a year ahead. The window is the same
+// for every allowed day and does not run past midnight.
+pub fn next_allowed(window: &CallingWindow, tz: Tz, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let first_day = after.with_timezone(&tz).date_naive();
+    for date in first_day.iter_days().take(366) {
+        if !window.days.contains(&date.weekday()) || window.holidays.contains(&date) {
+            continue;
+        }
+        let (start, end) = match (local(tz, date, window.start), local(tz, date, window.end)) {
+            (Some(start), Some(end)) => (start, end),
+            _ => continue,
+        };
+        if after < end {
+            return Some(after.max(start));
+        }
+    }
+    None
+}
+
+pub fn is_allowed(window: &CallingWindow, tz: Tz, at: DateTime<Utc>) -> bool {
+    next_allowed(window, tz, at) == Some(at)
+}
+
+// A local time that a DST change skips over is taken to be an hour later.
+fn local(tz: Tz, date: NaiveDate, time: NaiveTime) -> Option<DateTime<Utc>> {
+    let naive = date.and_time(time);
+    tz.from_local_datetime(&naive)
+        .earliest()
+        .or_else(|| {
+            tz.from_local_datetime(&(naive + Duration::hours(1)))
+                .earliest()
+        })
+        .map(|at| at.with_timezone(&Utc))
+}
@@ -0,0 +1,178 @@
+use crate::api::outbound::is_e164;
+use crate::api::retry::RetryPolicy;
+use crate::api::schedule::{
+    calendar,
+    jobs::{self, Job, JobStatus},
+};
+use crate::config::env;
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleRequest {
+    assistant_id: String,
+    phone_number_id: String,
+    customer_number: String,
+    customer_name: Option<String>,
+    // An IANA time zone such as "America/New_York".
+    time_zone: Option<String>,
+    // Either a local time in the customer's time zone, "2026-10-20T10:30",
+    // or an RFC 3339 time with an offset.
+    at: String,
+    // What to do when the call is unanswered; the `RETRY_*` settings when
+    // left out.
+    retry: Option<RetryPolicy>,
+}
+
+pub async fn create(body: web::Json<ScheduleRequest>) -> HttpResponse {
+    let request = body.into_inner();
+    if !is_e164(&request.customer_number) {
+        return bad_request(format!(
+            "{} is not an E.164 number",
+            request.customer_number
+        ));
+    }
+    if let Some(Err(error)) = request.retry.as_ref().map(|retry| retry.validate("retry")) {
+        return bad_request(error);
+    }
+    let config = env::load_env_config().schedule;
+    let time_zone = request
+        .time_zone
+        .unwrap_or_else(|| config.default_time_zone.clone());
+    let tz: Tz = match time_zone.parse() {
+        Ok(tz) => tz,
+        Err(_) => return bad_request(format!("unknown time zone {}", time_zone)),
+    };
+    let requested_at = match parse_time(&request.at, tz) {
+        Some(at) => at,
+        None => return bad_request(format!("invalid time {}", request.at)),
+    };
+
+    // Calls asked for in the past are placed as soon as the window allows.
+    let now = Utc::now();
+    let dial_at = match calendar::next_allowed(&config.calling_window, tz, requested_at.max(now)) {
+        Some(dial_at) => dial_at,
+        None => return bad_request("the calling window allows no time to call".to_string()),
+    };
+    let job = Job {
+        id: format!("scheduled-{:016x}", rand::random::<u64>()),
+        assistant_id: request.assistant_id,
+        phone_number_id: request.phone_number_id,
+        customer_number: request.customer_number,
+        customer_name: request.customer_name,
+        time_zone,
+        requested_at,
+        dial_at,
+        status: JobStatus::Scheduled,
+        call_id: None,
+        error: None,
+        retry: request
+            .retry
+            .unwrap_or_else(|| RetryPolicy::from(&env::load_env_config().retry)),
+        attempts: Vec::new(),
+        created_at: now,
+    };
+    if dial_at > requested_at.max(now) {
+        println!(
+            "Scheduled call {} moved from {} to {} to fit the calling window",
+            job.id, requested_at, dial_at
+        );
+    }
+
+    let mut jobs = jobs::jobs();
+    jobs.jobs.insert(job.id.clone(), job.clone());
+    jobs.save();
+    HttpResponse::Created().json(job)
+}
+
+pub async fn list() -> HttpResponse {
+    let jobs = jobs::jobs();
+    let mut list: Vec<&Job> = jobs.jobs.values().collect();
+    list.sort_by_key(|job| job.dial_at);
+    HttpResponse::Ok().json(list)
+}
+
+pub async fn get(id: web::Path<String>) -> HttpResponse {
+    match jobs::jobs().jobs.get(id.as_str()) {
+        Some(job) => HttpResponse::Ok().json(job),
+        None => not_found(),
+    }
+}
+
+// Only calls that have not been placed yet can be cancelled.
+pub async fn cancel(id: web::Path<String>) -> HttpResponse {
+    let mut jobs = jobs::jobs();
+    let job = match jobs.jobs.get_mut(id.as_str()) {
+        Some(job) => job,
+        None => return not_found(),
+    };
+    if job.status != JobStatus::Scheduled {
+        return HttpResponse::Conflict().json(json!({
+            "error": format!("the call is {:?}", job.status).to_lowercase(),
+        }));
+    }
+    job.status = JobStatus::Cancelled;
+    let job = job.clone();
+    jobs.save();
+    HttpResponse::Ok().json(job)
+}
+
+fn parse_time(at: &str, tz: Tz) -> Option<DateTime<Utc>> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(at) {
+        return Some(at.with_timezone(&Utc));
+    }
+    let local = NaiveDateTime::parse_from_str(at, "%Y-%m-%dT%H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(at, "%Y-%m-%dT%H:%M"))
+        .ok()?;
+    tz.from_local_datetime(&local)
+        .earliest()
+        .map(|at| at.with_timezone(&Utc))
+}
+
+fn bad_request(error: String) -> HttpResponse {
+    HttpResponse::BadRequest().json(json!({ "error": error }))
+}
+
+fn not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(json!({ "error": "no such scheduled call" }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{body::to_bytes, http::StatusCode};
+    use serde_json::Value;
+
+    async fn schedule(retry: Value) -> (StatusCode, Value) {
+        let request = serde_json::from_value(json!({
+            "assistantId": "assistant-1",
+            "phoneNumberId": "phone-1",
+            "customerNumber": "+14155550123",
+            "at": "2026-10-20T10:30",
+            "retry": retry,
+        }))
+        .unwrap();
+        let response = create(web::Json(request)).await;
+        let status = response.status();
+        let body = to_bytes(response.into_body()).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[actix_web::test]
+    async fn retry_policies_that_never_call_or_call_back_in_time_are_refused() {
+        let (status, body) = schedule(json!({"maxAttempts": 0, "spacingSecs": 60})).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("retry.maxAttempts"));
+
+        let (status, body) = schedule(json!({"maxAttempts": 3, "spacingSecs": -60})).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "retry.spacingSecs must not be negative");
+    }
+}
@@ -0,0 +1,145 @@
+use crate::api::retry::{Attempt, RetryPolicy};
+use crate::api::schedule::calendar;
+use crate::config::env;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum JobStatus {
+    Scheduled,
+    Dialing,
+    Placed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Job {
+    pub id: String,
+    pub assistant_id: String,
+    pub phone_number_id: String,
+    pub customer_number: String,
+    pub customer_name: Option<String>,
+    pub time_zone: String,
+    pub requested_at: DateTime<Utc>,
+    // When the call will be placed, after moving it into the calling window.
+    pub dial_at: DateTime<Utc>,
+    pub status: JobStatus,
+    pub call_id: Option<String>,
+    pub error: Option<String>,
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    // Every call placed for the job, oldest first.
+    #[serde(default)]
+    pub attempts: Vec<Attempt>,
+    pub created_at: DateTime<Utc>,
+}
+
+// Scheduled calls, kept in memory and written to `SCHEDULE_FILE` on every
+// change so they survive a restart.
+pub struct Jobs {
+    file: String,
+    pub jobs: HashMap<String, Job>,
+}
+
+pub fn jobs() -> MutexGuard<'static, Jobs> {
+    static JOBS: OnceLock<Mutex<Jobs>> = OnceLock::new();
+    JOBS.get_or_init(|| Mutex::new(Jobs::load(env::load_env_config().schedule.file)))
+        .lock()
+        .unwrap()
+}
+
+impl Jobs {
+    fn load(file: String) -> Self {
+        let mut jobs: HashMap<String, Job> = match fs::read_to_string(&file) {
+            Ok(text) => serde_json::from_str::<Vec<Job>>(&text)
+                .unwrap_or_else(|e| {
+                    eprintln!("Ignoring invalid schedule file {}: {}", file, e);
+                    Vec::new()
+                })
+                .into_iter()
+                .map(|job| (job.id.clone(), job))
+                .collect(),
+            Err(_) => HashMap::new(),
+        };
+        // A call that was being placed when the server stopped may have gone
+        // through, so it is not dialed again.
+        for job in jobs.values_mut() {
+            if job.status == JobStatus::Dialing {
+                job.status = JobStatus::Failed;
+                job.error = Some("the server stopped while placing the call".to_string());
+            }
+        }
+        println!("Loaded {} scheduled calls from {}", jobs.len(), file);
+        Jobs { file, jobs }
+    }
+
+    // Written to a temporary file first so a crash can't leave half a file.
+    pub fn save(&self) {
+        let mut jobs: Vec<&Job> = self.jobs.values().collect();
+        jobs.sort_by_key(|job| job.dial_at);
+        let temp = format!("{}.tmp", self.file);
+        let result = serde_json::to_string_pretty(&jobs)
+            .map_err(|e| e.to_string())
+            .and_then(|text| fs::write(&temp, text).map_err(|e| e.to_string()))
+            .and_then(|_| fs::rename(&temp, &self.file).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            eprintln!("Failed to save scheduled calls to {}: {}", self.file, e);
+        }
+    }
+}
+
+// Records how a scheduled call went. An unanswered call is scheduled again
+// while the job's retry policy allows, at the first time the calling window
+// permits after the spacing.
+pub fn call_ended(call_id: &str, ended_reason: &str) {
+    let mut jobs = jobs();
+    let job = match jobs
+        .jobs
+        .values_mut()
+        .find(|job| job.status == JobStatus::Placed && job.call_id.as_deref() == Some(call_id))
+    {
+        Some(job) => job,
+        None => return,
+    };
+    // Both the webhook and reconciliation report the end of a call.
+    if job
+        .attempts
+        .iter()
+        .any(|attempt| attempt.call_id == call_id)
+    {
+        return;
+    }
+    job.attempts.push(Attempt {
+        call_id: call_id.to_string(),
+        ended_reason: ended_reason.to_string(),
+        ended_at: Utc::now(),
+    });
+    if let Some(retry_at) = job.retry.retry_at(&job.attempts) {
+        let tz: Tz = job.time_zone.parse().unwrap_or(Tz::UTC);
+        let window = env::load_env_config().schedule.calling_window;
+        match calendar::next_allowed(&window, tz, retry_at) {
+            Some(dial_at) => {
+                println!(
+                    "Scheduled call {} will be tried again at {} ({}, attempt {} of {})",
+                    job.id,
+                    dial_at,
+                    ended_reason,
+                    job.attempts.len(),
+                    job.retry.max_attempts
+                );
+                job.status = JobStatus::Scheduled;
+                job.dial_at = dial_at;
+                job.call_id = None;
+            }
+            None => job.error = Some("the calling window allows no time to call".to_string()),
+        }
+    }
+    jobs.save();
+}
@@ -0,0 +1,4 @@
+pub mod calendar;
+pub mod index;
+pub mod jobs;
+pub mod runner;
@@ -0,0 +1,126 @@
+use crate::api::calls::lifecycle;
+use crate::api::registry::store as registry;
+use crate::api::retry;
+use crate::api::schedule::{
+    calendar,
+    jobs::{self, Job, JobStatus},
+};
+use crate::config::env;
+use crate::types::vapi::{AssistantOverrides, CreateCall, VapiCustomer};
+use crate::vapi_client::{VapiApi, VapiError};
+use actix_web::rt::time::sleep;
+use chrono::Utc;
+use chrono_tz::Tz;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+
+// Places scheduled calls as they fall due. The calling window is checked
+// again at dial time, since the server may have been down past the end of
+// it; a call outside the window is moved to the next time it allows.
+pub async fn run(vapi: Arc<dyn VapiApi>) {
+    loop {
+        let config = env::load_env_config();
+        for job in due() {
+            let tz: Tz = job.time_zone.parse().unwrap_or(Tz::UTC);
+            let now = Utc::now();
+            if !calendar::is_allowed(&config.schedule.calling_window, tz, now) {
+                let next = calendar::next_allowed(&config.schedule.calling_window, tz, now);
+                update(&job.id, |job| match next {
+                    Some(next) => {
+                        println!(
+                            "Scheduled call {} is outside the calling window, moved to {}",
+                            job.id, next
+                        );
+                        job.dial_at = next;
+                        job.status = JobStatus::Scheduled;
+                    }
+                    None => {
+                        job.status = JobStatus::Failed;
+                        job.error = Some("the calling window allows no time to call".to_string());
+                    }
+                });
+                continue;
+            }
+
+            if let Err(reason) = registry::check(&job.customer_number) {
+                update(&job.id, |job| {
+                    job.status = JobStatus::Failed;
+                    job.error = Some(reason);
+                });
+                continue;
+            }
+
+            let attempt = job.attempts.len() + 1;
+            let result = vapi
+                .create_call(
+                    &CreateCall {
+                        assistant_id: Some(job.assistant_id.clone()),
+                        phone_number_id: Some(job.phone_number_id.clone()),
+                        customer: Some(VapiCustomer {
+                            number: Some(job.customer_number.clone()),
+                            name: job.customer_name.clone(),
+                        }),
+                        assistant_overrides: job.retry.voicemail_message(attempt).map(|message| {
+                            AssistantOverrides {
+                                voicemail_message: Some(message),
+                                ..Default::default()
+                            }
+                        }),
+                        metadata: Some(json!({
+                            "scheduledCallId": job.id,
+                            "attempt": attempt,
+                        })),
+                        ..Default::default()
+                    },
+                    Some(&retry::idempotency_key(&job.id, attempt)),
+                )
+                .await
+                .and_then(|call| {
+                    lifecycle::track(&call, "created");
+                    call.id
+                        .ok_or_else(|| VapiError::Decode("the call has no id".to_string()))
+                });
+            update(&job.id, |job| match result {
+                Ok(call_id) => {
+                    println!("Scheduled call {} placed ({})", job.id, call_id);
+                    job.status = JobStatus::Placed;
+                    job.call_id = Some(call_id);
+                }
+                Err(e) => {
+                    eprintln!("Scheduled call {} failed: {}", job.id, e);
+                    job.status = JobStatus::Failed;
+                    job.error = Some(e.to_string());
+                }
+            });
+        }
+        sleep(Duration::from_millis(config.schedule.poll_interval_ms)).await;
+    }
+}
+
+// Jobs whose time has come, marked as dialing so a cancel can't race them.
+fn due() -> Vec<Job> {
+    let now = Utc::now();
+    let mut jobs = jobs::jobs();
+    let due: Vec<Job> = jobs
+        .jobs
+        .values_mut()
+        .filter(|job| job.status == JobStatus::Scheduled && job.dial_at <= now)
+        .map(|job| {
+            job.status = JobStatus::Dialing;
+            job.clone()
+        })
+        .collect();
+    if !due.is_empty() {
+        jobs.save();
+    }
+    due
+}
+
+fn update(id: &str, change: impl FnOnce(&mut Job)) {
+    let mut jobs = jobs::jobs();
+    if let Some(job) = jobs.jobs.get_mut(id) {
+        change(job);
+        jobs.save();
+    }
+}
@@ -1,3 +1,8 @@
+use crate::api::calls::lifecycle::{self, CallState};
+use crate::api::campaign;
+use crate::api::registry::opt_out;
+use crate::api::schedule;
+use crate::config::env;
 use crate::functions::{
     get_character_inspiration::{self, GetCharacterInspirationParams},
     get_random_name::{self, NameParams},
@@ -90,6 +95,15 @@ async fn handle_function_call(message: &VapiPayload) -> VapiResponse {
 
 fn handle_status_update(message: &VapiPayload) -> VapiResponse {
     // Handle status update event
+    if let VapiPayload::StatusUpdatePayload(data) = message {
+        match CallState::from_status(&data.status) {
+            Some(state) => lifecycle::transition(&data.call, state, "status-update"),
+            None => lifecycle::track(&data.call, "status-update"),
+        }
+        if let Some(call_id) = &data.call.id {
+            campaign::store::call_status(call_id, &data.status);
+        }
+    }
     VapiResponse::StatusUpdateMessageResponse({
         let mut map = StatusUpdateMessageResponse::new();
         map.insert("message".to_string(), "Status update handled".to_string());
@@ -99,6 +113,9 @@ fn handle_status_update(message: &VapiPayload) -> VapiResponse {
 
 fn handle_assistant_request(message: &VapiPayload) -> VapiResponse {
     // Handle assistant request event
+    if let VapiPayload::AssistantRequestPayload(data) = message {
+        lifecycle::track(&data.call, "assistant-request");
+    }
     VapiResponse::AssistantRequestMessageResponse(AssistantRequestMessageResponse {
         assistant: None,
         error: Some("Assistant request handled".to_string()),
@@ -107,6 +124,15 @@ fn handle_assistant_request(message: &VapiPayload) -> VapiResponse {
 
 fn handle_end_of_call_report(message: &VapiPayload) -> VapiResponse {
     // Handle end of call report event
+    if let VapiPayload::EndOfCallReportPayload(data) = message {
+        let ended_reason = data.ended_reason();
+        lifecycle::ended(&data.call, ended_reason, "end-of-call-report");
+        if let Some(call_id) = &data.call.id {
+            campaign::store::call_ended(call_id, ended_reason);
+            schedule::jobs::call_ended(call_id, ended_reason);
+        }
+        opt_out::from_report(&env::load_env_config().registry, data);
+    }
     VapiResponse::EndOfCallReportMessageResponse({
         let mut map = EndOfCallReportMessageResponse::new();
         map.insert(
@@ -137,6 +163,9 @@ fn handle_transcript(message: &VapiPayload) -> VapiResponse {
 
 fn handle_hang(message: &VapiPayload) -> VapiResponse {
     // Handle hang event
+    if let VapiPayload::HangPayload(data) = message {
+        lifecycle::event(&data.call, "hang");
+    }
     VapiResponse::HangMessageResponse({
         let mut map = HangMessageResponse::new();
         map.insert("message".to_string(), "Hang handled".to_string());
@@ -1,9 +1,30 @@
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
 use std::env;
 
 pub struct EnvConfig {
     pub weather: WeatherConfig,
     pub openai: OpenaiConfig,
     pub vapi: VapiConfig,
+    pub tools: ToolsConfig,
+    pub context: ContextConfig,
+    pub llm: LlmConfig,
+    pub guardrails: GuardrailsConfig,
+    pub normalizer: NormalizerConfig,
+    pub routing: RoutingConfig,
+    pub filler: FillerConfig,
+    pub cache: CacheConfig,
+    pub faq: FaqConfig,
+    pub structured_output: HashMap<String, StructuredOutputConfig>,
+    pub credentials: CredentialsConfig,
+    pub campaign: CampaignConfig,
+    pub schedule: ScheduleConfig,
+    pub registry: RegistryConfig,
+    pub calls: CallsConfig,
+    pub retry: RetryConfig,
+    pub auth: AuthConfig,
 }
 
 pub struct WeatherConfig {
@@ -18,9 +39,386 @@ pub struct OpenaiConfig {
 pub struct VapiConfig {
     pub base_url: String,
     pub api_key: String,
+    pub max_retries: u32,
+    pub retry_initial_ms: u64,
+}
+
+pub struct ToolsConfig {
+    pub server_side: Vec<String>,
+    pub max_iterations: usize,
+}
+
+#[derive(PartialEq)]
+pub enum ContextStrategy {
+    None,
+    DropOldest,
+    KeepLast,
+    Summarize,
+}
+
+pub struct ContextConfig {
+    pub strategy: ContextStrategy,
+    pub max_tokens: Option<usize>,
+    pub keep_last_turns: usize,
+    pub summary_model: String,
+    // The provider asked for summaries, whichever provider answers the turn.
+    pub summary_provider: LlmTarget,
+}
+
+pub struct LlmTarget {
+    pub provider: String,
+    pub base_url: String,
+    pub api_key: String,
+    pub model: Option<String>,
+    pub max_concurrency: Option<usize>,
+}
+
+pub struct LlmConfig {
+    pub fallback_chain: Vec<LlmTarget>,
+    pub first_token_timeout_ms: u64,
+    pub hedge_delay_ms: Option<u64>,
+    pub apology_message: String,
+    pub queue_limit: usize,
+    pub queue_timeout_ms: u64,
+    pub rate_limit_cooldown_ms: u64,
+    pub busy_message: String,
+}
+
+// Every provider other than openai needs `<PROVIDER>_BASE_URL` and
+// `<PROVIDER>_API_KEY` to be set. `<PROVIDER>_MAX_CONCURRENCY` caps the number
+// of requests in flight to it, falling back to `LLM_MAX_CONCURRENCY`.
+fn load_llm_target(provider: &str, model: Option<String>, openai_api_key: &str) -> LlmTarget {
+    let prefix = provider.to_uppercase().replace('-', "_");
+    let (default_base_url, default_api_key) = if provider == "openai" {
+        ("https://api.openai.com/v1", openai_api_key)
+    } else {
+        ("", "")
+    };
+    LlmTarget {
+        provider: provider.to_string(),
+        base_url: env::var(format!("{}_BASE_URL", prefix))
+            .unwrap_or_else(|_| default_base_url.to_string()),
+        api_key: env::var(format!("{}_API_KEY", prefix))
+            .unwrap_or_else(|_| default_api_key.to_string()),
+        model,
+        max_concurrency: env::var(format!("{}_MAX_CONCURRENCY", prefix))
+            .or_else(|_| env::var("LLM_MAX_CONCURRENCY"))
+            .ok()
+            .and_then(|value| value.parse().ok()),
+    }
+}
+
+// `CONTEXT_SUMMARY_MODEL` looks like `provider:model`, or is just the model
+// for one of OpenAI's.
+fn load_summary_model() -> (String, String) {
+    let value = env::var("CONTEXT_SUMMARY_MODEL").unwrap_or_else(|_| "gpt-3.5-turbo".to_string());
+    match value.split_once(':') {
+        Some((provider, model)) => (provider.to_string(), model.to_string()),
+        None => ("openai".to_string(), value),
+    }
+}
+
+// Entries look like `provider` or `provider:model`.
+fn load_fallback_chain(openai_api_key: &str) -> Vec<LlmTarget> {
+    env::var("LLM_FALLBACK_CHAIN")
+        .unwrap_or_else(|_| "openai".to_string())
+        .split(',')
+        .map(|entry| entry.trim())
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| match entry.split_once(':') {
+            Some((provider, model)) => {
+                load_llm_target(provider, Some(model.to_string()), openai_api_key)
+            }
+            None => load_llm_target(entry, None, openai_api_key),
+        })
+        .collect()
+}
+
+pub struct ModelRoute {
+    pub target: LlmTarget,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u16>,
+    pub weight: u32,
+}
+
+pub struct RoutingConfig {
+    pub aliases: HashMap<String, Vec<ModelRoute>>,
+}
+
+#[derive(Deserialize)]
+struct ModelRouteEntry {
+    provider: String,
+    model: String,
+    temperature: Option<f32>,
+    max_tokens: Option<u16>,
+    weight: Option<u32>,
+}
+
+// `MODEL_ALIASES` maps each alias to the routes it splits traffic between:
+// {"fast": [{"provider": "openai", "model": "gpt-3.5-turbo", "weight": 80}, ...]}
+fn load_model_aliases(openai_api_key: &str) -> HashMap<String, Vec<ModelRoute>> {
+    let entries: HashMap<String, Vec<ModelRouteEntry>> =
+        match serde_json::from_str(&env::var("MODEL_ALIASES").unwrap_or_else(|_| "{}".to_string()))
+        {
+            Ok(entries) => entries,
+            Err(e) => {
+                eprintln!("Ignoring invalid MODEL_ALIASES: {}", e);
+                HashMap::new()
+            }
+        };
+    entries
+        .into_iter()
+        .map(|(alias, routes)| {
+            let routes = routes
+                .into_iter()
+                .map(|route| ModelRoute {
+                    target: load_llm_target(&route.provider, Some(route.model), openai_api_key),
+                    temperature: route.temperature,
+                    max_tokens: route.max_tokens,
+                    weight: route.weight.unwrap_or(100),
+                })
+                .collect();
+            (alias, routes)
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum GuardrailAction {
+    Replace,
+    CutOff,
+    Fallback,
+}
+
+#[derive(Debug)]
+pub struct GuardrailRuleConfig {
+    pub patterns: Vec<String>,
+    pub literal: bool,
+    pub action: GuardrailAction,
+    pub replacement: String,
+}
+
+#[derive(Debug)]
+pub struct GuardrailsConfig {
+    pub rules: Vec<GuardrailRuleConfig>,
+    pub fallback: String,
+    pub hold_chars: usize,
+}
+
+fn load_guardrail_rule(
+    name: &str,
+    literal: bool,
+    default_action: GuardrailAction,
+    default_replacement: &str,
+) -> GuardrailRuleConfig {
+    let value = env::var(name).unwrap_or_default();
+    // Regexes may contain commas, so they are given as a JSON array instead.
+    let patterns = if literal {
+        value
+            .split(',')
+            .map(|pattern| pattern.trim().to_string())
+            .filter(|pattern| !pattern.is_empty())
+            .collect()
+    } else {
+        serde_json::from_str(&value).unwrap_or_default()
+    };
+    GuardrailRuleConfig {
+        patterns,
+        literal,
+        action: match env::var(format!("{}_ACTION", name)).as_deref() {
+            Ok("replace") => GuardrailAction::Replace,
+            Ok("cut") => GuardrailAction::CutOff,
+            Ok("fallback") => GuardrailAction::Fallback,
+            _ => default_action,
+        },
+        replacement: env::var(format!("{}_REPLACEMENT", name))
+            .unwrap_or_else(|_| default_replacement.to_string()),
+    }
+}
+
+pub struct NormalizerConfig {
+    pub enabled: bool,
+    pub locale: String,
+}
+
+#[derive(Deserialize)]
+pub struct FillerIntent {
+    pub intent: String,
+    pub keywords: Vec<String>,
+    pub phrases: Vec<String>,
+}
+
+pub struct FillerConfig {
+    pub delay_ms: Option<u64>,
+    pub intents: Vec<FillerIntent>,
+    pub default_phrases: Vec<String>,
+}
+
+pub struct CacheConfig {
+    pub enabled: bool,
+    pub capacity: usize,
+    pub ttl_secs: u64,
+    pub assistant_ttl_secs: HashMap<String, u64>,
+    pub dir: Option<String>,
+}
+
+pub struct FaqConfig {
+    pub file: Option<String>,
+    pub threshold: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructuredOutputConfig {
+    pub schema: Value,
+    pub speech_field: String,
+    #[serde(default = "default_max_retries")]
+    pub max_retries: usize,
+}
+
+fn default_max_retries() -> usize {
+    1
+}
+
+// `STRUCTURED_OUTPUT` is keyed by assistant id:
+// {"<assistant id>": {"schema": {...}, "speechField": "reply", "maxRetries": 1}}
+fn load_structured_output() -> HashMap<String, StructuredOutputConfig> {
+    match serde_json::from_str(&env::var("STRUCTURED_OUTPUT").unwrap_or_else(|_| "{}".to_string()))
+    {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Ignoring invalid STRUCTURED_OUTPUT: {}", e);
+            HashMap::new()
+        }
+    }
+}
+
+pub struct CredentialsConfig {
+    pub from_header: bool,
+    pub org_api_keys: HashMap<String, String>,
+}
+
+pub struct CampaignConfig {
+    pub dial_interval_ms: u64,
+    pub max_active_calls: usize,
+}
+
+// When calls may be placed, in the customer's local time.
+pub struct CallingWindow {
+    pub days: Vec<Weekday>,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    pub holidays: Vec<NaiveDate>,
+}
+
+// `CALLING_WINDOW` as it is written:
+// {"days": ["mon", "tue"], "start": "09:00", "end": "20:00", "holidays": ["2026-12-25"]}
+#[derive(Default, Deserialize)]
+struct CallingWindowJson {
+    days: Option<Vec<String>>,
+    start: Option<String>,
+    end: Option<String>,
+    holidays: Option<Vec<String>>,
+}
+
+// Without a calendar, calls are allowed from 8am to 9pm every day, the hours
+// the TCPA permits.
+fn load_calling_window() -> CallingWindow {
+    let json: CallingWindowJson = match env::var("CALLING_WINDOW") {
+        Ok(value) => serde_json::from_str(&value).unwrap_or_else(|e| {
+            eprintln!("Ignoring invalid CALLING_WINDOW: {}", e);
+            CallingWindowJson::default()
+        }),
+        Err(_) => CallingWindowJson::default(),
+    };
+    let time = |value: Option<String>, default: (u32, u32)| {
+        value
+            .and_then(|value| NaiveTime::parse_from_str(&value, "%H:%M").ok())
+            .or_else(|| NaiveTime::from_hms_opt(default.0, default.1, 0))
+            .unwrap_or_default()
+    };
+    CallingWindow {
+        days: match json.days {
+            Some(days) => days.iter().filter_map(|day| day.parse().ok()).collect(),
+            None => vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+                Weekday::Sat,
+                Weekday::Sun,
+            ],
+        },
+        start: time(json.start, (8, 0)),
+        end: time(json.end, (21, 0)),
+        holidays: json
+            .holidays
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|day| NaiveDate::parse_from_str(day, "%Y-%m-%d").ok())
+            .collect(),
+    }
+}
+
+pub struct ScheduleConfig {
+    pub file: String,
+    pub poll_interval_ms: u64,
+    pub default_time_zone: String,
+    pub calling_window: CallingWindow,
+}
+
+pub struct RegistryConfig {
+    pub file: String,
+    pub consent_required: bool,
+    pub stop_phrases: Vec<String>,
+}
+
+pub struct CallsConfig {
+    pub reconcile_interval_ms: u64,
+    pub reconcile_after_secs: i64,
+    // How long the custom LLM session of an ended call can still be read.
+    pub session_retention_secs: u64,
+}
+
+// The retry policy campaigns and scheduled calls get unless they set their
+// own. One attempt means unanswered calls are not retried.
+pub struct RetryConfig {
+    pub max_attempts: u32,
+    pub spacing_secs: i64,
+    pub voicemail_message: Option<String>,
+}
+
+// An API key, kept as the hex SHA-256 of the key itself.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKey {
+    pub id: String,
+    pub hash: String,
+    pub scopes: Vec<String>,
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+pub struct AuthConfig {
+    // Only for local development; every control endpoint is then open.
+    pub disabled: bool,
+    pub keys: Vec<ApiKey>,
+    pub keys_file: String,
+    pub jwt_secret: Option<String>,
+    // The server secret set on the Vapi org or assistant, which Vapi sends
+    // with every webhook as `X-Vapi-Secret`.
+    pub webhook_secret: Option<String>,
+    // Leaves the Vapi-facing routes open when Vapi is not set up to send a
+    // secret. Org API keys are still only used with the secret.
+    pub vapi_disabled: bool,
+    pub audit_file: String,
 }
 
 pub fn load_env_config() -> EnvConfig {
+    let openai_api_key = env::var("OPENAI_API_KEY").unwrap_or_else(|_| "".to_string());
+    let (summary_provider, summary_model) = load_summary_model();
+
     EnvConfig {
         weather: WeatherConfig {
             base_url: env::var("WEATHER_BASE_URL")
@@ -28,12 +426,230 @@ pub fn load_env_config() -> EnvConfig {
             api_key: env::var("WEATHER_API_KEY").unwrap_or_else(|_| "".to_string()),
         },
         openai: OpenaiConfig {
-            api_key: env::var("OPENAI_API_KEY").unwrap_or_else(|_| "".to_string()),
+            api_key: openai_api_key.clone(),
         },
         vapi: VapiConfig {
             base_url: env::var("VAPI_BASE_URL")
                 .unwrap_or_else(|_| "https://api.vapi.ai".to_string()),
             api_key: env::var("VAPI_API_KEY").unwrap_or_else(|_| "".to_string()),
+            max_retries: env::var("VAPI_MAX_RETRIES")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(3),
+            retry_initial_ms: env::var("VAPI_RETRY_INITIAL_MS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(500),
+        },
+        tools: ToolsConfig {
+            server_side: env::var("SERVER_SIDE_TOOLS")
+                .unwrap_or_default()
+                .split(',')
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect(),
+            max_iterations: env::var("TOOL_MAX_ITERATIONS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(3),
+        },
+        context: ContextConfig {
+            strategy: match env::var("CONTEXT_STRATEGY").as_deref() {
+                Ok("drop-oldest") => ContextStrategy::DropOldest,
+                Ok("keep-last") => ContextStrategy::KeepLast,
+                Ok("summarize") => ContextStrategy::Summarize,
+                _ => ContextStrategy::None,
+            },
+            max_tokens: env::var("CONTEXT_MAX_TOKENS")
+                .ok()
+                .and_then(|value| value.parse().ok()),
+            keep_last_turns: env::var("CONTEXT_KEEP_LAST_TURNS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(10),
+            summary_model,
+            summary_provider: load_llm_target(&summary_provider, None, &openai_api_key),
+        },
+        llm: LlmConfig {
+            fallback_chain: load_fallback_chain(&openai_api_key),
+            first_token_timeout_ms: env::var("LLM_FIRST_TOKEN_TIMEOUT_MS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(3000),
+            hedge_delay_ms: env::var("LLM_HEDGE_DELAY_MS")
+                .ok()
+                .and_then(|value| value.parse().ok()),
+            apology_message: env::var("LLM_APOLOGY_MESSAGE").unwrap_or_else(|_| {
+                "Sorry, I'm having some trouble on my end. Could you say that again?".to_string()
+            }),
+            queue_limit: env::var("LLM_QUEUE_LIMIT")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(8),
+            queue_timeout_ms: env::var("LLM_QUEUE_TIMEOUT_MS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(1500),
+            rate_limit_cooldown_ms: env::var("LLM_RATE_LIMIT_COOLDOWN_MS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(10000),
+            busy_message: env::var("LLM_BUSY_MESSAGE")
+                .unwrap_or_else(|_| "One moment please.".to_string()),
+        },
+        guardrails: GuardrailsConfig {
+            rules: vec![
+                load_guardrail_rule(
+                    "GUARDRAIL_BANNED_PHRASES",
+                    true,
+                    GuardrailAction::Fallback,
+                    "",
+                ),
+                load_guardrail_rule(
+                    "GUARDRAIL_COMPETITORS",
+                    true,
+                    GuardrailAction::Replace,
+                    "another provider",
+                ),
+                load_guardrail_rule("GUARDRAIL_PATTERNS", false, GuardrailAction::Replace, ""),
+            ],
+            fallback: env::var("GUARDRAIL_FALLBACK").unwrap_or_else(|_| {
+                "Sorry, I can't help with that. Is there anything else I can do for you?"
+                    .to_string()
+            }),
+            hold_chars: env::var("GUARDRAIL_HOLD_CHARS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(32),
+        },
+        normalizer: NormalizerConfig {
+            enabled: env::var("NORMALIZE_TEXT").is_ok_and(|value| value == "true"),
+            locale: env::var("NORMALIZE_LOCALE").unwrap_or_else(|_| "en-US".to_string()),
+        },
+        routing: RoutingConfig {
+            aliases: load_model_aliases(&openai_api_key),
+        },
+        filler: FillerConfig {
+            delay_ms: env::var("FILLER_DELAY_MS")
+                .ok()
+                .and_then(|value| value.parse().ok()),
+            // [{"intent": "booking", "keywords": ["book"], "phrases": ["Let me look at the calendar."]}]
+            intents: serde_json::from_str(&env::var("FILLER_INTENTS").unwrap_or_default())
+                .unwrap_or_default(),
+            default_phrases: serde_json::from_str(&env::var("FILLER_PHRASES").unwrap_or_default())
+                .unwrap_or_else(|_| vec!["Let me check that for you.".to_string()]),
+        },
+        cache: CacheConfig {
+            enabled: env::var("LLM_CACHE").is_ok_and(|value| value == "true"),
+            capacity: env::var("LLM_CACHE_CAPACITY")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(256),
+            ttl_secs: env::var("LLM_CACHE_TTL_SECS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(3600),
+            // {"<assistant id>": 600}; a TTL of 0 turns the cache off for that assistant.
+            assistant_ttl_secs: serde_json::from_str(
+                &env::var("LLM_CACHE_ASSISTANT_TTL_SECS").unwrap_or_default(),
+            )
+            .unwrap_or_default(),
+            dir: env::var("LLM_CACHE_DIR").ok(),
+        },
+        faq: FaqConfig {
+            file: env::var("FAQ_FILE").ok(),
+            threshold: env::var("FAQ_THRESHOLD")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(0.8),
+        },
+        structured_output: load_structured_output(),
+        credentials: CredentialsConfig {
+            from_header: env::var("UPSTREAM_KEY_FROM_HEADER").is_ok_and(|value| value == "true"),
+            // {"<org id>": "sk-..."}
+            org_api_keys: serde_json::from_str(&env::var("ORG_API_KEYS").unwrap_or_default())
+                .unwrap_or_default(),
+        },
+        campaign: CampaignConfig {
+            dial_interval_ms: env::var("CAMPAIGN_DIAL_INTERVAL_MS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(5000),
+            max_active_calls: env::var("CAMPAIGN_MAX_ACTIVE_CALLS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(1),
+        },
+        schedule: ScheduleConfig {
+            file: env::var("SCHEDULE_FILE").unwrap_or_else(|_| "scheduled_calls.json".to_string()),
+            poll_interval_ms: env::var("SCHEDULE_POLL_INTERVAL_MS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(15000),
+            default_time_zone: env::var("SCHEDULE_DEFAULT_TIME_ZONE")
+                .unwrap_or_else(|_| "UTC".to_string()),
+            calling_window: load_calling_window(),
+        },
+        registry: RegistryConfig {
+            file: env::var("REGISTRY_FILE").unwrap_or_else(|_| "registry.json".to_string()),
+            consent_required: env::var("CONSENT_REQUIRED").is_ok_and(|value| value == "true"),
+            // Regexes, matched case-insensitively against what the customer said.
+            stop_phrases: serde_json::from_str(
+                &env::var("STOP_CALLING_PHRASES").unwrap_or_default(),
+            )
+            .unwrap_or_else(|_| {
+                vec![
+                    r"stop calling".to_string(),
+                    r"(don'?t|do not|never) call (me|this number)".to_string(),
+                    r"(remove|take) (me|this number|my number) (off|from)".to_string(),
+                    r"put me on (your|the) do not call".to_string(),
+                ]
+            }),
+        },
+        calls: CallsConfig {
+            reconcile_interval_ms: env::var("CALL_RECONCILE_INTERVAL_MS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(60000),
+            reconcile_after_secs: env::var("CALL_RECONCILE_AFTER_SECS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(120),
+            session_retention_secs: env::var("SESSION_RETENTION_SECS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(600),
+        },
+        retry: RetryConfig {
+            max_attempts: env::var("RETRY_MAX_ATTEMPTS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(1),
+            spacing_secs: env::var("RETRY_SPACING_SECS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(3600),
+            voicemail_message: env::var("RETRY_VOICEMAIL_MESSAGE").ok(),
+        },
+        auth: AuthConfig {
+            disabled: env::var("AUTH_DISABLED").is_ok_and(|value| value == "true"),
+            // [{"id": "dialer", "hash": "<sha256 of the key>", "scopes": ["calls:create"]}]
+            keys: match env::var("API_KEYS") {
+                Ok(value) => serde_json::from_str(&value).unwrap_or_else(|e| {
+                    eprintln!("Ignoring invalid API_KEYS: {}", e);
+                    Vec::new()
+                }),
+                Err(_) => Vec::new(),
+            },
+            keys_file: env::var("API_KEYS_FILE").unwrap_or_else(|_| "api_keys.json".to_string()),
+            jwt_secret: env::var("JWT_SECRET")
+                .ok()
+                .filter(|secret| !secret.is_empty()),
+            webhook_secret: env::var("VAPI_WEBHOOK_SECRET")
+                .ok()
+                .filter(|secret| !secret.is_empty()),
+            vapi_disabled: env::var("VAPI_AUTH_DISABLED").is_ok_and(|value| value == "true"),
+            audit_file: env::var("AUDIT_FILE").unwrap_or_else(|_| "audit.log".to_string()),
         },
     }
 }
@@ -5,3 +5,31 @@ pub mod get_random_name;
 pub use self::fetch_keyword::find_keywords;
 pub use self::get_character_inspiration::get_character_inspiration;
 pub use self::get_random_name::get_random_name;
+
+use serde_json::Value;
+
+// Runs one of the functions above by the name the model knows it under.
+// Returns None when the name is not one we can execute locally.
+pub async fn call_function(name: &str, parameters: Value) -> Option<String> {
+    let result = match name {
+        "getRandomName" => match serde_json::from_value(parameters) {
+            Ok(params) => get_random_name(params)
+                .await
+                .unwrap_or_else(|_| "Failed to get random name".to_string()),
+            Err(_) => "Not enough information provided to generate name.".to_string(),
+        },
+        "getCharacterInspiration" => {
+            let params = serde_json::from_value(parameters).unwrap_or_default();
+            get_character_inspiration(params).await.result
+        }
+        "findKeywords" => match serde_json::from_value(parameters) {
+            Ok(params) => match find_keywords(params).await {
+                Ok(keywords) => keywords.join(", "),
+                Err(_) => "Failed to find keywords".to_string(),
+            },
+            Err(_) => "Not enough information provided to find keywords.".to_string(),
+        },
+        _ => return None,
+    };
+    Some(result)
+}
@@ -2,10 +2,13 @@ mod api;
 pub mod config;
 pub mod functions;
 pub mod types;
+pub mod vapi_client;
 
 use actix_web::{get, post, web, App, HttpResponse, HttpServer, Responder};
 use api::routes::config;
 use dotenv::dotenv;
+use std::sync::Arc;
+use vapi_client::{VapiApi, VapiClient};
 
 #[get("/")]
 async fn hello() -> impl Responder {
@@ -24,8 +27,13 @@ async fn manual_hello() -> impl Responder {
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
-    HttpServer::new(|| {
+    api::auth::keys::check_vapi_secret(&config::env::load_env_config().auth);
+    let vapi: Arc<dyn VapiApi> = Arc::new(VapiClient::new(&config::env::load_env_config().vapi));
+    actix_web::rt::spawn(api::schedule::runner::run(vapi.clone()));
+    actix_web::rt::spawn(api::calls::reconcile::run(vapi.clone()));
+    HttpServer::new(move || {
         App::new()
+            .app_data(web::Data::from(vapi.clone()))
             .service(hello)
             .service(echo)
             .route("/hey", web::get().to(manual_hello))
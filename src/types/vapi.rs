@@ -1,96 +1,222 @@
 use serde::de::{self, Deserializer, MapAccess, Visitor};
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::{Map, Value};
 use std::any::Any;
 use std::collections::HashMap;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Model {
-    pub model: String,
+    pub model: Option<String>,
+    #[serde(alias = "systemPrompt")]
     pub system_prompt: Option<String>,
     pub temperature: Option<f32>,
     pub functions: Option<Vec<Function>>,
-    pub provider: String,
+    pub provider: Option<String>,
     pub url: Option<String>,
+    // The rest of Vapi's model, such as `messages` and `tools`, kept as given.
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Function {
     pub name: String,
+    #[serde(alias = "isAsync")]
     pub is_async: Option<bool>,
     pub description: Option<String>,
     pub parameters: Option<Value>,
+    // Such as the `server` a function is called on.
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
 }
 
 pub type PlayHTEmotion = String;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Voice {
     pub provider: String,
-    pub voice_id: String,
+    #[serde(alias = "voiceId")]
+    pub voice_id: Option<String>,
     pub speed: Option<f32>,
     pub stability: Option<f32>,
+    #[serde(alias = "similarityBoost")]
     pub similarity_boost: Option<f32>,
-    pub style: Option<i32>,
+    pub style: Option<f32>,
+    #[serde(alias = "useSpeakerBoost")]
     pub use_speaker_boost: Option<bool>,
     pub temperature: Option<f32>,
     pub emotion: Option<PlayHTEmotion>,
-    pub voice_guidance: Option<i32>,
-    pub style_guidance: Option<i32>,
-    pub text_guidance: Option<i32>,
+    #[serde(alias = "voiceGuidance")]
+    pub voice_guidance: Option<f32>,
+    #[serde(alias = "styleGuidance")]
+    pub style_guidance: Option<f32>,
+    #[serde(alias = "textGuidance")]
+    pub text_guidance: Option<f32>,
+    // Provider-specific settings such as 11labs' `model`.
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Assistant {
     pub name: Option<String>,
     pub transcriber: Option<Transcriber>,
     pub model: Option<Model>,
     pub voice: Option<Voice>,
     pub language: Option<String>,
+    #[serde(alias = "forwardingPhoneNumber")]
     pub forwarding_phone_number: Option<String>,
+    #[serde(alias = "firstMessage")]
     pub first_message: Option<String>,
+    #[serde(alias = "voicemailMessage")]
     pub voicemail_message: Option<String>,
+    #[serde(alias = "endCallMessage")]
     pub end_call_message: Option<String>,
+    #[serde(alias = "endCallPhrases")]
     pub end_call_phrases: Option<Vec<String>>,
+    #[serde(alias = "interruptionsEnabled")]
     pub interruptions_enabled: Option<bool>,
+    #[serde(alias = "recordingEnabled")]
     pub recording_enabled: Option<bool>,
+    #[serde(alias = "endCallFunctionEnabled")]
     pub end_call_function_enabled: Option<bool>,
+    #[serde(alias = "dialKeypadFunctionEnabled")]
     pub dial_keypad_function_enabled: Option<bool>,
+    #[serde(alias = "fillersEnabled")]
     pub fillers_enabled: Option<bool>,
-    pub client_messages: Option<Vec<HashMap<String, String>>>,
-    pub server_messages: Option<Vec<HashMap<String, String>>>,
-    pub silence_timeout_seconds: Option<i32>,
-    pub response_delay_seconds: Option<i32>,
+    #[serde(alias = "clientMessages")]
+    pub client_messages: Option<Vec<String>>,
+    #[serde(alias = "serverMessages")]
+    pub server_messages: Option<Vec<String>>,
+    #[serde(alias = "silenceTimeoutSeconds")]
+    pub silence_timeout_seconds: Option<f32>,
+    #[serde(alias = "responseDelaySeconds")]
+    pub response_delay_seconds: Option<f32>,
+    #[serde(alias = "liveTranscriptsEnabled")]
     pub live_transcripts_enabled: Option<bool>,
     pub keywords: Option<Vec<String>>,
+    #[serde(alias = "parentId")]
     pub parent_id: Option<String>,
+    #[serde(alias = "serverUrl")]
     pub server_url: Option<String>,
+    #[serde(alias = "serverUrlSecret")]
     pub server_url_secret: Option<String>,
     pub id: Option<String>,
+    #[serde(alias = "orgId")]
     pub org_id: Option<String>,
+    #[serde(alias = "createdAt")]
     pub created_at: Option<String>,
+    #[serde(alias = "updatedAt")]
     pub updated_at: Option<String>,
+    // Fields of Vapi's assistant not modelled above, such as `analysisPlan`
+    // or `voicemailDetection`, so a transient assistant reaches Vapi whole.
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Transcriber {
     pub provider: String,
     pub model: Option<String>,
     pub keywords: Option<Vec<String>>,
+    // Such as `language`.
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+// The assistant types above keep the snake_case JSON that `/api/inbound` and
+// the assistant-request webhook have always returned, and read Vapi's
+// camelCase too. Whatever is sent to Vapi's API goes through `to_vapi`, which
+// renames the fields the way Vapi spells them. Only the types' own fields are
+// renamed, never the keys of maps inside them such as function parameters.
+pub trait ToVapi {
+    fn to_vapi(&self) -> Value;
+}
+
+impl ToVapi for Function {
+    fn to_vapi(&self) -> Value {
+        camel_case_fields(serde_json::to_value(self).unwrap_or_default())
+    }
+}
+
+impl ToVapi for Model {
+    fn to_vapi(&self) -> Value {
+        let mut model = camel_case_fields(serde_json::to_value(self).unwrap_or_default());
+        if let Some(functions) = &self.functions {
+            model["functions"] = functions.iter().map(ToVapi::to_vapi).collect();
+        }
+        model
+    }
+}
+
+impl ToVapi for Voice {
+    fn to_vapi(&self) -> Value {
+        camel_case_fields(serde_json::to_value(self).unwrap_or_default())
+    }
+}
+
+impl ToVapi for Transcriber {
+    fn to_vapi(&self) -> Value {
+        camel_case_fields(serde_json::to_value(self).unwrap_or_default())
+    }
+}
+
+impl ToVapi for Assistant {
+    fn to_vapi(&self) -> Value {
+        let mut assistant = camel_case_fields(serde_json::to_value(self).unwrap_or_default());
+        if let Some(model) = &self.model {
+            assistant["model"] = model.to_vapi();
+        }
+        if let Some(voice) = &self.voice {
+            assistant["voice"] = voice.to_vapi();
+        }
+        if let Some(transcriber) = &self.transcriber {
+            assistant["transcriber"] = transcriber.to_vapi();
+        }
+        assistant
+    }
+}
+
+// For `serialize_with` on the fields of types that are sent to Vapi.
+fn as_vapi<T: ToVapi, S: Serializer>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error> {
+    value.as_ref().map(ToVapi::to_vapi).serialize(serializer)
+}
+
+fn camel_case_fields(value: Value) -> Value {
+    match value {
+        Value::Object(fields) => Value::Object(
+            fields
+                .into_iter()
+                .map(|(name, value)| {
+                    let mut parts = name.split('_');
+                    let mut camel = parts.next().unwrap_or_default().to_string();
+                    for part in parts {
+                        let mut chars = part.chars();
+                        camel.extend(chars.next().map(|c| c.to_ascii_uppercase()));
+                        camel.push_str(chars.as_str());
+                    }
+                    (camel, value)
+                })
+                .collect(),
+        ),
+        value => value,
+    }
 }
 
 pub type VapiCallStatus = String;
 
+// Times are epoch milliseconds; secondsFromStart is fractional.
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ConversationMessage {
     pub role: String,
     pub message: Option<String>,
     pub name: Option<String>,
     pub args: Option<String>,
     pub result: Option<String>,
-    pub time: i64,
-    pub end_time: Option<i64>,
-    pub seconds_from_start: i64,
+    pub time: Option<f64>,
+    pub end_time: Option<f64>,
+    pub seconds_from_start: Option<f64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -128,17 +254,30 @@ pub struct OpenAIFunctionCall {
     pub parameters: serde_json::Value,
 }
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct EndOfCallReportPayload {
     pub call: VapiCall,
     #[serde(rename = "type")]
     pub payload_type: String,
-    pub ended_reason: String,
+    pub ended_reason: Option<String>,
+    #[serde(default)]
     pub transcript: String,
+    #[serde(default)]
     pub messages: Vec<ConversationMessage>,
-    pub summary: String,
+    pub summary: Option<String>,
     pub recording_url: Option<String>,
 }
 
+impl EndOfCallReportPayload {
+    // Vapi sends the reason on the report, and sometimes only on the call.
+    pub fn ended_reason(&self) -> &str {
+        self.ended_reason
+            .as_deref()
+            .or(self.call.ended_reason.as_deref())
+            .unwrap_or_default()
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct HangPayload {
     pub call: VapiCall,
@@ -164,9 +303,191 @@ pub struct TranscriptPayload {
     pub transcript: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct VapiCall {
-    // Define the fields for VapiCall here
+    pub id: Option<String>,
+    pub org_id: Option<String>,
+    #[serde(rename = "type")]
+    pub call_type: Option<String>,
+    pub status: Option<String>,
+    pub assistant_id: Option<String>,
+    pub squad_id: Option<String>,
+    pub phone_number_id: Option<String>,
+    pub customer: Option<VapiCustomer>,
+    pub metadata: Option<Value>,
+    pub ended_reason: Option<String>,
+    pub monitor: Option<VapiCallMonitor>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+// Where a live call can be listened to and controlled.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VapiCallMonitor {
+    pub listen_url: Option<String>,
+    pub control_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VapiCustomer {
+    pub number: Option<String>,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VapiPhoneNumber {
+    pub id: Option<String>,
+    pub number: Option<String>,
+    pub name: Option<String>,
+    pub provider: Option<String>,
+    pub assistant_id: Option<String>,
+    pub squad_id: Option<String>,
+    pub created_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Squad {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub members: Vec<SquadMember>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SquadMember {
+    pub assistant_id: Option<String>,
+    #[serde(serialize_with = "as_vapi")]
+    pub assistant: Option<Assistant>,
+    pub assistant_overrides: Option<Value>,
+    pub assistant_destinations: Option<Vec<Value>>,
+}
+
+// Changes to a stored assistant for a single call. Only the fields the
+// server itself reads are typed; the rest of Vapi's overrides, such as
+// `recordingEnabled`, are passed through to Vapi as they were given.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssistantOverrides {
+    pub first_message: Option<String>,
+    // Filled into `{{name}}` placeholders in the assistant's prompts and
+    // messages.
+    pub variable_values: Option<Map<String, Value>>,
+    #[serde(serialize_with = "as_vapi")]
+    pub voice: Option<Voice>,
+    pub model: Option<ModelOverrides>,
+    pub voicemail_message: Option<String>,
+    pub end_call_message: Option<String>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+// Overrides only what is given, so `{"temperature": 0.2}` keeps the
+// assistant's provider, model and prompt.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelOverrides {
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub system_prompt: Option<String>,
+    pub temperature: Option<f32>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+// The body of `POST /call/phone`, which places an outbound phone call.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateCall {
+    pub name: Option<String>,
+    pub assistant_id: Option<String>,
+    #[serde(serialize_with = "as_vapi")]
+    pub assistant: Option<Assistant>,
+    pub assistant_overrides: Option<AssistantOverrides>,
+    pub squad_id: Option<String>,
+    pub phone_number_id: Option<String>,
+    pub customer: Option<VapiCustomer>,
+    pub metadata: Option<Value>,
+}
+
+// What Vapi sends to a custom LLM alongside the OpenAI request.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CallContext {
+    pub call: Option<VapiCall>,
+    pub metadata: Option<Value>,
+    #[serde(rename = "phoneNumber")]
+    pub phone_number: Option<VapiPhoneNumber>,
+}
+
+impl CallContext {
+    pub fn call_id(&self) -> Option<&str> {
+        self.call.as_ref().and_then(|call| call.id.as_deref())
+    }
+
+    pub fn assistant_id(&self) -> Option<&str> {
+        self.call
+            .as_ref()
+            .and_then(|call| call.assistant_id.as_deref())
+    }
+
+    pub fn org_id(&self) -> Option<&str> {
+        self.call.as_ref().and_then(|call| call.org_id.as_deref())
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CustomLlmRequest {
+    #[serde(flatten)]
+    pub completion: async_openai::types::CreateChatCompletionRequest,
+    #[serde(flatten)]
+    pub context: CallContext,
+}
+
+// async-openai parses messages as an untagged enum, so every message with
+// string content comes out as a system message and loses fields such as
+// `tool_call_id`. Messages are parsed by their role here instead.
+impl<'de> Deserialize<'de> for CustomLlmRequest {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use async_openai::types::ChatCompletionRequestMessage as Message;
+
+        let mut value = Value::deserialize(deserializer)?;
+        let messages = match value.get_mut("messages").map(Value::take) {
+            Some(Value::Array(messages)) => messages,
+            _ => return Err(de::Error::missing_field("messages")),
+        };
+        let messages = messages
+            .into_iter()
+            .map(|message| {
+                let parsed = match message.get("role").and_then(Value::as_str) {
+                    Some("system") => serde_json::from_value(message).map(Message::System),
+                    Some("user") => serde_json::from_value(message).map(Message::User),
+                    Some("assistant") => serde_json::from_value(message).map(Message::Assistant),
+                    Some("tool") => serde_json::from_value(message).map(Message::Tool),
+                    Some("function") => serde_json::from_value(message).map(Message::Function),
+                    _ => serde_json::from_value(message),
+                };
+                parsed.map_err(de::Error::custom)
+            })
+            .collect::<Result<Vec<_>, D::Error>>()?;
+        value["messages"] = Value::Array(Vec::new());
+
+        let mut completion: async_openai::types::CreateChatCompletionRequest =
+            serde_json::from_value(value.clone()).map_err(de::Error::custom)?;
+        completion.messages = messages;
+        let context = serde_json::from_value(value).map_err(de::Error::custom)?;
+        Ok(CustomLlmRequest {
+            completion,
+            context,
+        })
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -361,3 +682,56 @@ pub enum VapiResponse {
     HangMessageResponse(HangMessageResponse),
     EndOfCallReportMessageResponse(EndOfCallReportMessageResponse),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn assistants_parse_as_vapi_returns_them() {
+        let assistant: Assistant =
+            serde_json::from_str(include_str!("../../tests/fixtures/assistant.json")).unwrap();
+        assert_eq!(
+            assistant.first_message.as_deref(),
+            Some("Hi, this is Paula calling about your appointment tomorrow.")
+        );
+        assert_eq!(assistant.response_delay_seconds, Some(0.4));
+        assert_eq!(assistant.client_messages.unwrap()[0], "transcript");
+        assert_eq!(assistant.server_messages.unwrap()[0], "end-of-call-report");
+        let model = assistant.model.unwrap();
+        assert_eq!(model.provider.as_deref(), Some("openai"));
+        assert_eq!(model.system_prompt, None);
+        assert_eq!(assistant.voice.unwrap().voice_id.as_deref(), Some("paula"));
+    }
+
+    #[test]
+    fn end_of_call_reports_parse_as_vapi_sends_them() {
+        let report = json!({
+            "type": "end-of-call-report",
+            "call": {"id": "call-1", "customer": {"number": "+15550000001"}},
+            "endedReason": "customer-did-not-answer",
+            "recordingUrl": "https://example.com/call-1.wav",
+            "messages": [
+                {"role": "bot", "message": "Hi there", "time": 1729350000123.0, "secondsFromStart": 0.84},
+                {"role": "user", "message": "Please stop calling", "time": 1729350002456.5, "endTime": 1729350003001.0, "secondsFromStart": 3.17}
+            ]
+        });
+        let report = match serde_json::from_value(report).unwrap() {
+            VapiPayload::EndOfCallReportPayload(report) => report,
+            other => panic!("parsed as {:?}", other),
+        };
+        assert_eq!(report.ended_reason(), "customer-did-not-answer");
+        assert_eq!(report.messages[1].seconds_from_start, Some(3.17));
+        assert_eq!(report.transcript, "");
+        assert_eq!(report.summary, None);
+
+        // Older reports only carry the reason on the call.
+        let report: EndOfCallReportPayload = serde_json::from_value(json!({
+            "type": "end-of-call-report",
+            "call": {"id": "call-2", "endedReason": "voicemail"},
+        }))
+        .unwrap();
+        assert_eq!(report.ended_reason(), "voicemail");
+    }
+}
@@ -0,0 +1,42 @@
+use serde_json::Value;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum VapiError {
+    // The request did not get a response.
+    Network(String),
+    // Vapi answered with a non-2xx status; the body is kept as Vapi sent it.
+    Api { status: u16, body: Value },
+    // A 2xx response that is not the JSON we expected.
+    Decode(String),
+    // Something about the call itself, such as ending a call that has no
+    // control URL.
+    Invalid(String),
+}
+
+impl VapiError {
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, VapiError::Api { status: 404, .. })
+    }
+}
+
+impl fmt::Display for VapiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VapiError::Network(message) => write!(f, "could not reach Vapi: {}", message),
+            VapiError::Api { status, body } => write!(f, "Vapi returned {}: {}", status, body),
+            VapiError::Decode(message) => write!(f, "unexpected response from Vapi: {}", message),
+            VapiError::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<reqwest::Error> for VapiError {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_decode() {
+            VapiError::Decode(error.to_string())
+        } else {
+            VapiError::Network(error.to_string())
+        }
+    }
+}
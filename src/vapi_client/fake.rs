@@ -0,0 +1,215 @@
+use crate::types::vapi::{Assistant, CreateCall, Squad, ToVapi, VapiCall, VapiPhoneNumber};
+use crate::vapi_client::{Found, ListQuery, Page, VapiApi, VapiError};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+// An in-process stand-in for Vapi. Phone numbers, assistants and squads are
+// only known once added, and every call placed is kept along with the JSON
+// the real client would have sent, so tests can check both. Assistants are
+// kept as the JSON Vapi would return and decoded the way the client decodes
+// them, so a fixture taken from Vapi fails here just as it would there.
+#[derive(Default)]
+pub struct FakeVapi {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    calls: Vec<VapiCall>,
+    // The bodies of `POST /call/phone`, in the order they were sent.
+    sent: Vec<Value>,
+    idempotency_keys: HashMap<String, String>,
+    assistants: Vec<Value>,
+    phone_numbers: Vec<VapiPhoneNumber>,
+    squads: Vec<Squad>,
+}
+
+impl FakeVapi {
+    pub fn new() -> Self {
+        FakeVapi::default()
+    }
+
+    pub fn with_phone_number(self, id: &str) -> Self {
+        self.state().phone_numbers.push(VapiPhoneNumber {
+            id: Some(id.to_string()),
+            ..Default::default()
+        });
+        self
+    }
+
+    pub fn with_assistant(self, id: &str) -> Self {
+        self.with_assistant_json(json!({ "id": id }))
+    }
+
+    pub fn with_assistant_json(self, assistant: Value) -> Self {
+        self.state().assistants.push(assistant);
+        self
+    }
+
+    pub fn sent(&self) -> Vec<Value> {
+        self.state().sent.clone()
+    }
+
+    // Moves a placed call on, as Vapi would once it rang or ended.
+    pub fn set_status(&self, id: &str, status: &str, ended_reason: Option<&str>) {
+        if let Some(call) = self
+            .state()
+            .calls
+            .iter_mut()
+            .find(|call| call.id.as_deref() == Some(id))
+        {
+            call.status = Some(status.to_string());
+            call.ended_reason = ended_reason.map(str::to_string);
+        }
+    }
+
+    fn assistant(&self, id: &str) -> Result<Value, VapiError> {
+        self.state()
+            .assistants
+            .iter()
+            .find(|assistant| assistant["id"] == id)
+            .cloned()
+            .ok_or_else(|| not_found("assistant", id))
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+}
+
+fn not_found(kind: &str, id: &str) -> VapiError {
+    VapiError::Api {
+        status: 404,
+        body: json!({ "message": format!("{} {} not found", kind, id), "statusCode": 404 }),
+    }
+}
+
+fn decode<T: DeserializeOwned>(value: Value) -> Result<T, VapiError> {
+    serde_json::from_value(value).map_err(|e| VapiError::Decode(e.to_string()))
+}
+
+fn find<T: Clone>(items: &[T], id: &str, item_id: impl Fn(&T) -> Option<&str>) -> Option<T> {
+    items.iter().find(|item| item_id(item) == Some(id)).cloned()
+}
+
+#[async_trait]
+impl VapiApi for FakeVapi {
+    async fn create_call(
+        &self,
+        call: &CreateCall,
+        idempotency_key: Option<&str>,
+    ) -> Result<VapiCall, VapiError> {
+        let mut state = self.state();
+        if let Some(id) = idempotency_key.and_then(|key| state.idempotency_keys.get(key)) {
+            let id = id.clone();
+            return Ok(find(&state.calls, &id, |call| call.id.as_deref()).unwrap());
+        }
+        let body = serde_json::to_value(call).map_err(|e| VapiError::Invalid(e.to_string()))?;
+        let placed = VapiCall {
+            id: Some(format!("call-{}", state.calls.len() + 1)),
+            call_type: Some("outboundPhoneCall".to_string()),
+            status: Some("queued".to_string()),
+            assistant_id: call.assistant_id.clone(),
+            phone_number_id: call.phone_number_id.clone(),
+            customer: call.customer.clone(),
+            metadata: call.metadata.clone(),
+            ..Default::default()
+        };
+        if let (Some(key), Some(id)) = (idempotency_key, &placed.id) {
+            state.idempotency_keys.insert(key.to_string(), id.clone());
+        }
+        state.sent.push(body);
+        state.calls.push(placed.clone());
+        Ok(placed)
+    }
+
+    async fn get_call(&self, id: &str) -> Result<VapiCall, VapiError> {
+        find(&self.state().calls, id, |call| call.id.as_deref())
+            .ok_or_else(|| not_found("call", id))
+    }
+
+    async fn list_calls(&self, query: &ListQuery) -> Result<Page<VapiCall>, VapiError> {
+        Ok(Page::new(self.state().calls.clone(), query))
+    }
+
+    async fn end_call(&self, id: &str) -> Result<(), VapiError> {
+        self.get_call(id).await?;
+        self.set_status(id, "ended", Some("assistant-ended-call"));
+        Ok(())
+    }
+
+    async fn create_assistant(&self, assistant: &Assistant) -> Result<Assistant, VapiError> {
+        let mut created = assistant.to_vapi();
+        let mut state = self.state();
+        created["id"] = json!(format!("assistant-{}", state.assistants.len() + 1));
+        state.assistants.push(created.clone());
+        decode(created)
+    }
+
+    async fn get_assistant(&self, id: &str) -> Result<Assistant, VapiError> {
+        decode(self.assistant(id)?)
+    }
+
+    async fn find_assistant(&self, id: &str) -> Result<Found, VapiError> {
+        decode(self.assistant(id)?)
+    }
+
+    async fn list_assistants(&self, query: &ListQuery) -> Result<Page<Assistant>, VapiError> {
+        let assistants = self.state().assistants.clone();
+        Ok(Page::new(decode(Value::Array(assistants))?, query))
+    }
+
+    async fn update_assistant(
+        &self,
+        id: &str,
+        assistant: &Assistant,
+    ) -> Result<Assistant, VapiError> {
+        let mut state = self.state();
+        let stored = state
+            .assistants
+            .iter_mut()
+            .find(|stored| stored["id"] == id)
+            .ok_or_else(|| not_found("assistant", id))?;
+        if let (Value::Object(stored), Value::Object(changes)) = (&mut *stored, assistant.to_vapi())
+        {
+            stored.extend(changes.into_iter().filter(|(_, value)| !value.is_null()));
+        }
+        decode(stored.clone())
+    }
+
+    async fn delete_assistant(&self, id: &str) -> Result<(), VapiError> {
+        let mut state = self.state();
+        let before = state.assistants.len();
+        state.assistants.retain(|assistant| assistant["id"] != id);
+        if state.assistants.len() == before {
+            return Err(not_found("assistant", id));
+        }
+        Ok(())
+    }
+
+    async fn get_phone_number(&self, id: &str) -> Result<VapiPhoneNumber, VapiError> {
+        find(&self.state().phone_numbers, id, |number| {
+            number.id.as_deref()
+        })
+        .ok_or_else(|| not_found("phone number", id))
+    }
+
+    async fn list_phone_numbers(
+        &self,
+        query: &ListQuery,
+    ) -> Result<Page<VapiPhoneNumber>, VapiError> {
+        Ok(Page::new(self.state().phone_numbers.clone(), query))
+    }
+
+    async fn get_squad(&self, id: &str) -> Result<Squad, VapiError> {
+        find(&self.state().squads, id, |squad| squad.id.as_deref())
+            .ok_or_else(|| not_found("squad", id))
+    }
+
+    async fn list_squads(&self, query: &ListQuery) -> Result<Page<Squad>, VapiError> {
+        Ok(Page::new(self.state().squads.clone(), query))
+    }
+}
@@ -0,0 +1,264 @@
+use crate::config::env::VapiConfig;
+use crate::types::vapi::{Assistant, CreateCall, Squad, ToVapi, VapiCall, VapiPhoneNumber};
+use crate::vapi_client::{Found, ListQuery, Listed, Page, VapiApi, VapiError};
+use actix_web::rt::time::sleep;
+use async_trait::async_trait;
+use backoff::backoff::Backoff;
+use chrono::{DateTime, Utc};
+use reqwest::{header::HeaderMap, Client, Method, RequestBuilder, StatusCode};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+const MAX_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+pub struct VapiClient {
+    http: Client,
+    base_url: String,
+    api_key: String,
+    max_retries: u32,
+    retry_initial: Duration,
+}
+
+impl VapiClient {
+    pub fn new(config: &VapiConfig) -> Self {
+        VapiClient {
+            http: Client::new(),
+            base_url: config.base_url.trim_end_matches('/').to_string(),
+            api_key: config.api_key.clone(),
+            max_retries: config.max_retries,
+            retry_initial: Duration::from_millis(config.retry_initial_ms),
+        }
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.http
+            .request(method, format!("{}{}", self.base_url, path))
+            .bearer_auth(&self.api_key)
+    }
+
+    // Sends the request, retrying it when Vapi could not be reached, is rate
+    // limiting us or failed on its side. Waits follow a jittered exponential
+    // backoff, or the `Retry-After` Vapi sent. Requests that create something
+    // carry an idempotency key, so a retry can't create it twice.
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, VapiError> {
+        let mut backoff = backoff::ExponentialBackoffBuilder::new()
+            .with_initial_interval(self.retry_initial)
+            .with_max_interval(MAX_RETRY_INTERVAL)
+            .with_max_elapsed_time(None)
+            .build();
+        let mut attempt = 0;
+        loop {
+            let this_try = request
+                .try_clone()
+                .ok_or_else(|| VapiError::Invalid("the request cannot be sent".to_string()))?;
+            let (error, retry_after) = match this_try.send().await {
+                Ok(resp) if resp.status().is_success() => {
+                    let text = resp.text().await?;
+                    // Some endpoints answer with no body at all.
+                    let text = if text.is_empty() { "null" } else { &text };
+                    return serde_json::from_str(text)
+                        .map_err(|e| VapiError::Decode(e.to_string()));
+                }
+                Ok(resp) => {
+                    let status = resp.status();
+                    let retry_after = retry_after(resp.headers());
+                    let text = resp.text().await?;
+                    let error = VapiError::Api {
+                        status: status.as_u16(),
+                        body: serde_json::from_str(&text).unwrap_or(Value::String(text)),
+                    };
+                    if !retryable(status) {
+                        return Err(error);
+                    }
+                    (error, retry_after)
+                }
+                Err(e) if e.is_connect() || e.is_timeout() => (e.into(), None),
+                Err(e) => return Err(e.into()),
+            };
+
+            // A `Retry-After` longer than the longest backoff is not waited
+            // out; the caller is told Vapi is busy instead.
+            attempt += 1;
+            let delay = match retry_after.or_else(|| backoff.next_backoff()) {
+                Some(delay) if attempt <= self.max_retries && delay <= MAX_RETRY_INTERVAL => delay,
+                _ => return Err(error),
+            };
+            eprintln!(
+                "Vapi request failed ({}), retry {} of {} in {}ms",
+                error,
+                attempt,
+                self.max_retries,
+                delay.as_millis()
+            );
+            sleep(delay).await;
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, VapiError> {
+        self.send(self.request(Method::GET, path)).await
+    }
+
+    async fn list<T: DeserializeOwned + Listed>(
+        &self,
+        path: &str,
+        query: &ListQuery,
+    ) -> Result<Page<T>, VapiError> {
+        let items = self
+            .send(self.request(Method::GET, path).query(query))
+            .await?;
+        Ok(Page::new(items, query))
+    }
+
+    async fn write<B: Serialize, T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        body: &B,
+    ) -> Result<T, VapiError> {
+        let body = serde_json::to_value(body).map_err(|e| VapiError::Invalid(e.to_string()))?;
+        self.send(self.request(method, path).json(&without_nulls(body)))
+            .await
+    }
+
+    async fn create<B: Serialize, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+        idempotency_key: Option<&str>,
+    ) -> Result<T, VapiError> {
+        let key = idempotency_key
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("{:032x}", rand::random::<u128>()));
+        let body = serde_json::to_value(body).map_err(|e| VapiError::Invalid(e.to_string()))?;
+        self.send(
+            self.request(Method::POST, path)
+                .header("Idempotency-Key", key)
+                .json(&without_nulls(body)),
+        )
+        .await
+    }
+}
+
+fn retryable(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+// `Retry-After` is either a number of seconds or an HTTP date.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get("Retry-After")?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?;
+    (at.with_timezone(&Utc) - Utc::now()).to_std().ok()
+}
+
+// Unset fields are left out rather than sent as null, which Vapi would reject
+// or, on an update, take as clearing the field.
+fn without_nulls(value: Value) -> Value {
+    match value {
+        Value::Object(fields) => Value::Object(
+            fields
+                .into_iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(name, value)| (name, without_nulls(value)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(without_nulls).collect()),
+        value => value,
+    }
+}
+
+#[async_trait]
+impl VapiApi for VapiClient {
+    async fn create_call(
+        &self,
+        call: &CreateCall,
+        idempotency_key: Option<&str>,
+    ) -> Result<VapiCall, VapiError> {
+        self.create("/call/phone", call, idempotency_key).await
+    }
+
+    async fn get_call(&self, id: &str) -> Result<VapiCall, VapiError> {
+        self.get(&format!("/call/{}", id)).await
+    }
+
+    async fn list_calls(&self, query: &ListQuery) -> Result<Page<VapiCall>, VapiError> {
+        self.list("/call", query).await
+    }
+
+    async fn end_call(&self, id: &str) -> Result<(), VapiError> {
+        let call = self.get_call(id).await?;
+        let control_url = call
+            .monitor
+            .and_then(|monitor| monitor.control_url)
+            .ok_or_else(|| VapiError::Invalid(format!("call {} has no control URL", id)))?;
+        let request = self
+            .http
+            .post(control_url)
+            .json(&json!({ "type": "end-call" }));
+        self.send::<Value>(request).await.map(|_| ())
+    }
+
+    async fn create_assistant(&self, assistant: &Assistant) -> Result<Assistant, VapiError> {
+        self.create("/assistant", &assistant.to_vapi(), None).await
+    }
+
+    async fn get_assistant(&self, id: &str) -> Result<Assistant, VapiError> {
+        self.get(&format!("/assistant/{}", id)).await
+    }
+
+    async fn find_assistant(&self, id: &str) -> Result<Found, VapiError> {
+        self.get(&format!("/assistant/{}", id)).await
+    }
+
+    async fn list_assistants(&self, query: &ListQuery) -> Result<Page<Assistant>, VapiError> {
+        self.list("/assistant", query).await
+    }
+
+    async fn update_assistant(
+        &self,
+        id: &str,
+        assistant: &Assistant,
+    ) -> Result<Assistant, VapiError> {
+        self.write(
+            Method::PATCH,
+            &format!("/assistant/{}", id),
+            &assistant.to_vapi(),
+        )
+        .await
+    }
+
+    async fn delete_assistant(&self, id: &str) -> Result<(), VapiError> {
+        self.send::<Value>(self.request(Method::DELETE, &format!("/assistant/{}", id)))
+            .await
+            .map(|_| ())
+    }
+
+    async fn get_phone_number(&self, id: &str) -> Result<VapiPhoneNumber, VapiError> {
+        self.get(&format!("/phone-number/{}", id)).await
+    }
+
+    async fn list_phone_numbers(
+        &self,
+        query: &ListQuery,
+    ) -> Result<Page<VapiPhoneNumber>, VapiError> {
+        self.list("/phone-number", query).await
+    }
+
+    async fn get_squad(&self, id: &str) -> Result<Squad, VapiError> {
+        self.get(&format!("/squad/{}", id)).await
+    }
+
+    async fn list_squads(&self, query: &ListQuery) -> Result<Page<Squad>, VapiError> {
+        self.list("/squad", query).await
+    }
+}
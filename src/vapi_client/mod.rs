@@ -0,0 +1,125 @@
+pub mod error;
+#[cfg(test)]
+pub mod fake;
+pub mod http;
+
+use crate::types::vapi::{Assistant, CreateCall, Squad, VapiCall, VapiPhoneNumber};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+pub use error::VapiError;
+pub use http::VapiClient;
+
+// The parts of the Vapi REST API the server uses. Handlers get it from app
+// data as `web::Data<dyn VapiApi>`, so an in-process fake can stand in for
+// `VapiClient`, or `VAPI_BASE_URL` can point the real one at a local server.
+#[async_trait]
+pub trait VapiApi: Send + Sync {
+    // Retrying with the same idempotency key never places a second call. A
+    // random key is used when none is given.
+    async fn create_call(
+        &self,
+        call: &CreateCall,
+        idempotency_key: Option<&str>,
+    ) -> Result<VapiCall, VapiError>;
+    async fn get_call(&self, id: &str) -> Result<VapiCall, VapiError>;
+    async fn list_calls(&self, query: &ListQuery) -> Result<Page<VapiCall>, VapiError>;
+    // Hangs up a live call through its control URL.
+    async fn end_call(&self, id: &str) -> Result<(), VapiError>;
+
+    async fn create_assistant(&self, assistant: &Assistant) -> Result<Assistant, VapiError>;
+    async fn get_assistant(&self, id: &str) -> Result<Assistant, VapiError>;
+    // Only reads the id, so an assistant with fields `Assistant` does not
+    // model can still be found.
+    async fn find_assistant(&self, id: &str) -> Result<Found, VapiError>;
+    async fn list_assistants(&self, query: &ListQuery) -> Result<Page<Assistant>, VapiError>;
+    // Only the fields that are set are changed.
+    async fn update_assistant(
+        &self,
+        id: &str,
+        assistant: &Assistant,
+    ) -> Result<Assistant, VapiError>;
+    async fn delete_assistant(&self, id: &str) -> Result<(), VapiError>;
+
+    async fn get_phone_number(&self, id: &str) -> Result<VapiPhoneNumber, VapiError>;
+    async fn list_phone_numbers(
+        &self,
+        query: &ListQuery,
+    ) -> Result<Page<VapiPhoneNumber>, VapiError>;
+
+    async fn get_squad(&self, id: &str) -> Result<Squad, VapiError>;
+    async fn list_squads(&self, query: &ListQuery) -> Result<Page<Squad>, VapiError>;
+}
+
+// Vapi's list endpoints return the newest items first, `limit` at a time.
+// Older pages are asked for with `createdAtLt` set to the oldest item seen.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at_lt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at_gt: Option<String>,
+    // Calls only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assistant_id: Option<String>,
+}
+
+// Something Vapi has, with none of its other fields decoded.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Found {
+    pub id: String,
+}
+
+const DEFAULT_PAGE_SIZE: u32 = 100;
+
+#[derive(Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    // The query for the next page, when this one was full.
+    pub next: Option<ListQuery>,
+}
+
+pub trait Listed {
+    fn created_at(&self) -> Option<&str>;
+}
+
+impl<T: Listed> Page<T> {
+    pub fn new(items: Vec<T>, query: &ListQuery) -> Self {
+        let full = items.len() as u32 >= query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+        let next = match items.last().and_then(Listed::created_at) {
+            Some(oldest) if full => Some(ListQuery {
+                created_at_lt: Some(oldest.to_string()),
+                ..query.clone()
+            }),
+            _ => None,
+        };
+        Page { items, next }
+    }
+}
+
+impl Listed for VapiCall {
+    fn created_at(&self) -> Option<&str> {
+        self.created_at.as_deref()
+    }
+}
+
+impl Listed for Assistant {
+    fn created_at(&self) -> Option<&str> {
+        self.created_at.as_deref()
+    }
+}
+
+impl Listed for VapiPhoneNumber {
+    fn created_at(&self) -> Option<&str> {
+        self.created_at.as_deref()
+    }
+}
+
+impl Listed for Squad {
+    fn created_at(&self) -> Option<&str> {
+        self.created_at.as_deref()
+    }
+}